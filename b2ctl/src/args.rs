@@ -12,9 +12,23 @@ pub(crate) struct Cli {
 #[derive(Subcommand)]
 pub(crate) enum Commands {
     /// Check if the config is **semantically** valid.
-    Check { config: PathBuf },
+    Check {
+        config: PathBuf,
+        /// Check a fragment in `b2.conf.d`, which holds only items.
+        #[arg(short, long)]
+        fragment: bool,
+    },
     /// Send sample config file to stdout.
     Sample,
+    /// Send JSON Schema of config to stdout, for editors to check configs with.
+    Schema,
+    /// Upgrade a config to the current schema version.
+    Migrate {
+        config: PathBuf,
+        /// File to write the upgraded config to, instead of replacing the input.
+        #[arg(short)]
+        output: Option<PathBuf>,
+    },
     /// Generate hashed password for use with b2.
     Password {
         /// Memory size for argon2id, in KB.
@@ -24,4 +38,24 @@ pub(crate) enum Commands {
         #[arg(short)]
         t: Option<u32>,
     },
+    /// Generate a key pair for signing configs.
+    ///
+    /// The secret key is written to a file, and the public key is sent to stdout,
+    /// to be put in the file at B2_CONFIG_KEYS when building b2.
+    Keygen {
+        /// File to write the secret key to.
+        key: PathBuf,
+    },
+    /// Sign a config, writing the signature next to it as `<config>.sig`.
+    ///
+    /// For `Config` variable, put the signature in `ConfigSig` variable.
+    Sign {
+        /// Secret key generated by `keygen`.
+        #[arg(short)]
+        key: PathBuf,
+        config: PathBuf,
+        /// Sign a fragment in `b2.conf.d`, which holds only items.
+        #[arg(short, long)]
+        fragment: bool,
+    },
 }
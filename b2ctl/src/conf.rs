@@ -1,42 +1,31 @@
-use config::{BootItem, BootTarget, Config};
+use config::{BootItem, BootTarget, Config, SCHEMA_VERSION};
 use serde::{Deserialize, Serialize};
 
 pub fn fallback_menu() -> Config {
     Config {
+        version: SCHEMA_VERSION,
         name: Some("b2 Menu".to_owned()),
         message: Some("".to_owned()),
         items: vec![
             #[cfg(target_os = "uefi")]
-            BootItem {
-                name: "Linux".to_owned(),
-                target: BootTarget::EFI {
+            BootItem::new(
+                "Linux",
+                BootTarget::EFI {
                     path: "/linux/vmlinuz".to_owned(),
                     cmdline: Some("initrd=\\linux\\initrd.gz".to_owned()),
                 },
-            },
-            BootItem {
-                name: "Reboot".to_owned(),
-                target: BootTarget::Reboot,
-            },
-            BootItem {
-                name: "Poweroff".to_owned(),
-                target: BootTarget::Poweroff,
-            },
-            BootItem {
-                name: "Firmware Setup".to_owned(),
-                target: BootTarget::FirmwareSetup,
-            },
-            BootItem {
-                name: "Exit".to_owned(),
-                target: BootTarget::Exit,
-            },
-            BootItem {
-                name: "Debug Info".to_owned(),
-                target: BootTarget::Debug,
-            },
+            ),
+            BootItem::new("Reboot", BootTarget::Reboot),
+            BootItem::new("Poweroff", BootTarget::Poweroff),
+            BootItem::new("Firmware Setup", BootTarget::FirmwareSetup),
+            BootItem::new("Exit", BootTarget::Exit),
+            BootItem::new("Debug Info", BootTarget::Debug),
         ],
-        default: 0,
+        default: 0.into(),
         timeout: None,
         password: None,
+        drivers: None,
+        variables: None,
+        profiles: None,
     }
 }
@@ -7,27 +7,59 @@ use argon2::{
     Argon2, PasswordHasher,
 };
 use clap::Parser;
-use config::Config;
+use config::signature::{decode_hex, encode_hex, SIGNATURE_SUFFIX};
+use config::Fragment;
 use constant_time_eq::constant_time_eq;
+use ed25519_dalek::{Signer, SigningKey, SECRET_KEY_LENGTH};
 use rand::rngs::OsRng;
+use std::io::Write;
 pub mod args;
 pub mod conf;
+pub mod migrate;
 
 fn main() {
     let args = Cli::parse();
 
     match args.command {
         //args::Commands::Wizard { output } => {}
-        args::Commands::Check { config } => {
-            let config = std::fs::read_to_string(config)
-                .expect("this file should only contain ASCII characters");
-            let config: Result<Config, _> = serde_json::from_str(&config);
-            match config {
-                Ok(_) => {
+        args::Commands::Check {
+            config,
+            fragment: true,
+        } => {
+            let data = std::fs::read(&config).unwrap();
+            match config::parse::parse::<Fragment>(&data) {
+                Ok(fragment) => {
+                    for i in fragment.compatibility_warnings() {
+                        eprintln!("Warning: {}", i);
+                    }
                     eprintln!("This file is valid");
                 }
                 Err(e) => {
-                    eprintln!("Invalid file: {}", e);
+                    eprintln!("Invalid file {}: {}", config.display(), e);
+                    std::process::exit(1)
+                }
+            }
+        }
+        args::Commands::Check { config, .. } => {
+            let data = std::fs::read(&config).unwrap();
+            match config::parse_config(&data) {
+                Ok(config) => {
+                    for i in config.compatibility_warnings() {
+                        eprintln!("Warning: {}", i);
+                    }
+                    for i in config.variable_errors() {
+                        eprintln!("Warning: {}", i);
+                    }
+                    if config.version < config::SCHEMA_VERSION {
+                        eprintln!(
+                            "Warning: config is of schema version {}, run `b2ctl migrate` to upgrade it.",
+                            config.version
+                        );
+                    }
+                    eprintln!("This file is valid");
+                }
+                Err(e) => {
+                    eprintln!("Invalid file {}: {}", config.display(), e);
                     std::process::exit(1)
                 }
             }
@@ -37,18 +69,47 @@ fn main() {
 
         }
         */
+        args::Commands::Schema => {
+            let schema = config::schema::schema();
+            println!("{}", serde_json::to_string_pretty(&schema).unwrap());
+        }
         args::Commands::Sample => {
             let mut conf = conf::fallback_menu();
-            conf.items.push(config::BootItem {
-                name: "Linux".to_owned(),
-                target: config::BootTarget::EFI {
+            conf.items.push(config::BootItem::new(
+                "Linux",
+                config::BootTarget::EFI {
                     path: "/linux".to_owned(),
                     cmdline: Some("initrd=/initrd".to_owned()),
                 },
-            });
+            ));
             let s = serde_json::to_string_pretty(&conf);
             println!("{}", s.unwrap());
         }
+        args::Commands::Migrate { config, output } => {
+            let text = std::fs::read_to_string(&config).unwrap();
+            let mut value: serde_json::Value = serde_json::from_str(&text).unwrap_or_else(|e| {
+                eprintln!("Invalid file: {}", e);
+                std::process::exit(1)
+            });
+            match migrate::migrate(&mut value) {
+                Ok((from, to)) if from == to => eprintln!("Config is already of version {}.", to),
+                Ok((from, to)) => {
+                    let output = output.unwrap_or(config);
+                    let text = serde_json::to_string_pretty(&value).unwrap();
+                    std::fs::write(&output, text + "\n").unwrap();
+                    eprintln!(
+                        "Upgraded from version {} to {}, written to {}",
+                        from,
+                        to,
+                        output.display()
+                    );
+                }
+                Err(e) => {
+                    eprintln!("Failed to migrate: {}", e);
+                    std::process::exit(1)
+                }
+            }
+        }
         args::Commands::Password { m, t } => {
             let password = rpassword::prompt_password("Password: ").unwrap();
             let password_repeat = rpassword::prompt_password("Repeat Password: ").unwrap();
@@ -61,5 +122,54 @@ fn main() {
             let pwhash = argon2.hash_password(password.as_bytes(), &salt).unwrap();
             println!("\n{}", pwhash);
         }
+        args::Commands::Keygen { key } => {
+            let mut options = std::fs::OpenOptions::new();
+            options.write(true).create_new(true);
+            // The secret key is only for its owner.
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+            let secret = SigningKey::generate(&mut OsRng);
+            let written = options
+                .open(&key)
+                .and_then(|mut x| x.write_all(encode_hex(secret.as_bytes()).as_bytes()));
+            if let Err(e) = written {
+                eprintln!("Failed to write {}: {}", key.display(), e);
+                std::process::exit(1);
+            }
+            println!("{}", encode_hex(secret.verifying_key().as_bytes()));
+        }
+        args::Commands::Sign {
+            key,
+            config,
+            fragment,
+        } => {
+            let secret = std::fs::read_to_string(&key)
+                .ok()
+                .and_then(|x| decode_hex(&x))
+                .and_then(|x| <[u8; SECRET_KEY_LENGTH]>::try_from(x).ok())
+                .unwrap_or_else(|| {
+                    eprintln!(
+                        "{} is not a key generated by `b2ctl keygen`.",
+                        key.display()
+                    );
+                    std::process::exit(1)
+                });
+            let secret = SigningKey::from_bytes(&secret);
+            let data = std::fs::read(&config).unwrap();
+            // A signed broken config would still be refused by b2.
+            let parsed = match fragment {
+                true => config::parse::parse::<Fragment>(&data).map(|_| ()),
+                false => config::parse_config(&data).map(|_| ()),
+            };
+            if let Err(e) = parsed {
+                eprintln!("Invalid file {}: {}", config.display(), e);
+                std::process::exit(1)
+            }
+            let mut output = config.into_os_string();
+            output.push(SIGNATURE_SUFFIX);
+            let signature = secret.sign(&data);
+            std::fs::write(&output, encode_hex(&signature.to_bytes())).unwrap();
+            eprintln!("Signature written to {}", output.to_string_lossy());
+        }
     }
 }
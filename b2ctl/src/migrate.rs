@@ -0,0 +1,150 @@
+//! Upgrade configs to the current schema version.
+//!
+//! Configs are upgraded one version at a time, as JSON values, so fields renamed or moved
+//! in a version are seen as they were written.
+
+use config::{Config, SCHEMA_VERSION};
+use serde_json::{json, Value};
+
+/// Migration from version `n` to `n + 1`, at index `n`.
+const MIGRATIONS: &[fn(&mut Value)] = &[
+    // 0 to 1: `version` is added. Options and hotkeys of version 0 are read as they are.
+    |_| {},
+    // 1 to 2: submenus are added, and `default` may be a path into them.
+    |_| {},
+    // 2 to 3: entries may have conditions.
+    |_| {},
+    // 3 to 4: variables are added, so `$` in cmdlines and paths is escaped.
+    |config| {
+        if let Some(items) = config.get_mut("items") {
+            escape_dollars(items);
+        }
+    },
+    // 4 to 5: profiles are added.
+    |_| {},
+    // 5 to 6: multiboot2 target is added.
+    |_| {},
+    // 6 to 7: Linux targets may carry a device tree.
+    |_| {},
+];
+
+/// Escape `$` as `$$` in strings variables are substituted in, of items and their submenus.
+///
+/// These are the strings of `BootTarget::strings_mut`.
+fn escape_dollars(items: &mut Value) {
+    fn escape(text: Option<&mut Value>) {
+        if let Some(Value::String(text)) = text {
+            *text = text.replace('$', "$$");
+        }
+    }
+    // Images are in the form of `ImageLocation`, which only has a path of type `Path`.
+    fn escape_image(image: Option<&mut Value>) {
+        escape(image.and_then(|x| x.get_mut("path")));
+    }
+    for item in items.as_array_mut().into_iter().flatten() {
+        let Some(target) = item.get_mut("target") else {
+            continue;
+        };
+        if let Some(items) = target.get_mut("items") {
+            escape_dollars(items);
+        }
+        escape(target.get_mut("path"));
+        escape(target.get_mut("cmdline"));
+        escape_image(target.get_mut("kernel"));
+        escape_image(target.get_mut("devicetree"));
+        for initrd in target
+            .get_mut("initrd")
+            .and_then(Value::as_array_mut)
+            .into_iter()
+            .flatten()
+        {
+            escape_image(Some(initrd));
+        }
+        for module in target
+            .get_mut("modules")
+            .and_then(Value::as_array_mut)
+            .into_iter()
+            .flatten()
+        {
+            escape_image(module.get_mut("image"));
+            escape(module.get_mut("cmdline"));
+        }
+    }
+}
+
+/// Schema version of a config, which is 0 without `version`.
+pub fn version_of(config: &Value) -> Result<u32, String> {
+    match config.get("version") {
+        None => Ok(0),
+        Some(v) => v
+            .as_u64()
+            .and_then(|x| u32::try_from(x).ok())
+            .ok_or_else(|| format!("invalid version {}", v)),
+    }
+}
+
+/// Upgrade a config to `SCHEMA_VERSION`, and check the result.
+///
+/// Returns versions it was upgraded from and to.
+pub fn migrate(config: &mut Value) -> Result<(u32, u32), String> {
+    if !config.is_object() {
+        return Err("config is not a JSON object".to_owned());
+    }
+    let from = version_of(config)?;
+    if from > SCHEMA_VERSION {
+        return Err(format!(
+            "config is of version {}, newer than {} of this b2ctl",
+            from, SCHEMA_VERSION
+        ));
+    }
+    for (version, migration) in MIGRATIONS.iter().enumerate().skip(from as usize) {
+        migration(config);
+        config["version"] = json!(version + 1);
+    }
+    serde_json::from_value::<Config>(config.clone())
+        .map_err(|e| format!("migrated config is invalid: {}", e))?;
+    Ok((from, SCHEMA_VERSION))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_variables() {
+        let mut config = json!({"version": 3, "default": 0, "name": null, "message": null,
+            "timeout": null, "password": null, "items": [
+            {"name": "a", "target": {"type": "submenu", "items": [
+                {"name": "b", "target": {"type": "efi", "path": "/x", "cmdline": "a=${b}"}}
+            ]}}
+        ]});
+        assert_eq!(migrate(&mut config), Ok((3, SCHEMA_VERSION)));
+        assert_eq!(
+            config["items"][0]["target"]["items"][0]["target"]["cmdline"],
+            "a=$${b}"
+        );
+    }
+
+    #[test]
+    fn escape_images_and_modules() {
+        let path = |x: &str| json!({"type": "Path", "path": x});
+        let mut items = json!([
+            {"name": "linux", "target": {"type": "linux", "kernel": path("/$k"),
+                "initrd": [path("/$i"), {"type": "Flash"}], "devicetree": path("/$d"),
+                "cmdline": "$c"}},
+            {"name": "xen", "target": {"type": "multiboot2", "kernel": path("/$x"),
+                "cmdline": "", "modules": [{"image": path("/$m"), "cmdline": "$a"}]}}
+        ]);
+        escape_dollars(&mut items);
+        let linux = &items[0]["target"];
+        assert_eq!(linux["kernel"]["path"], "/$$k");
+        assert_eq!(linux["initrd"][0]["path"], "/$$i");
+        assert_eq!(linux["initrd"][1], json!({"type": "Flash"}));
+        assert_eq!(linux["devicetree"]["path"], "/$$d");
+        assert_eq!(linux["cmdline"], "$$c");
+        let xen = &items[1]["target"];
+        assert_eq!(xen["kernel"]["path"], "/$$x");
+        assert_eq!(xen["modules"][0]["image"]["path"], "/$$m");
+        assert_eq!(xen["modules"][0]["cmdline"], "$$a");
+    }
+}
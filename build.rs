@@ -0,0 +1,116 @@
+//! Build script of b2.
+//!
+//! With `embedded-config`, the config file is checked here, so a broken config never makes into a build.
+//! With `signed-config`, public keys for config signatures are compiled in.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=B2_EMBEDDED_CONFIG");
+    println!("cargo:rerun-if-env-changed=B2_EMBEDDED_POLICY");
+    println!("cargo:rerun-if-env-changed=B2_CONFIG_KEYS");
+    println!("cargo:rerun-if-env-changed=B2_SIGNATURE_POLICY");
+    if env::var_os("CARGO_FEATURE_EMBEDDED_CONFIG").is_some() {
+        embed_config();
+    }
+    if env::var_os("CARGO_FEATURE_SIGNED_CONFIG").is_some() {
+        embed_keys();
+    }
+}
+
+fn embed_config() {
+    let root = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap());
+    let path = root.join(env::var_os("B2_EMBEDDED_CONFIG").unwrap_or_else(|| {
+        panic!("embedded-config: set B2_EMBEDDED_CONFIG to the config file to embed.")
+    }));
+    println!("cargo:rerun-if-changed={}", path.display());
+    let data = fs::read(&path)
+        .unwrap_or_else(|e| panic!("embedded-config: failed to read {}: {}", path.display(), e));
+    // Checked as `b2ctl check` does.
+    let config = config::parse_config(&data)
+        .unwrap_or_else(|e| panic!("embedded-config: {} is invalid: {}", path.display(), e));
+    if config.items.is_empty() {
+        panic!("embedded-config: {} has no item.", path.display());
+    }
+    if config.item(config.default.indexes()).is_none() {
+        println!(
+            "cargo:warning=embedded-config: default item {:?} is out of range, the first item is used.",
+            config.default.indexes()
+        );
+    }
+
+    let policy = match env::var("B2_EMBEDDED_POLICY").as_deref() {
+        Ok("fallback") | Err(_) => "Fallback",
+        Ok("override") => "Override",
+        Ok(k) => panic!(
+            "embedded-config: unknown B2_EMBEDDED_POLICY {}, expected `fallback` or `override`.",
+            k
+        ),
+    };
+    let out = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("embedded_config.rs");
+    fs::write(
+        out,
+        format!(
+            "pub const EMBEDDED_CONFIG: &[u8] = include_bytes!({:?});\n\
+             pub const EMBEDDED_POLICY: EmbeddedPolicy = EmbeddedPolicy::{};\n",
+            path.display().to_string(),
+            policy
+        ),
+    )
+    .unwrap();
+}
+
+fn embed_keys() {
+    let root = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap());
+    let path = root.join(env::var_os("B2_CONFIG_KEYS").unwrap_or_else(|| {
+        panic!("signed-config: set B2_CONFIG_KEYS to a file of public keys, see `b2ctl keygen`.")
+    }));
+    println!("cargo:rerun-if-changed={}", path.display());
+    let text = fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("signed-config: failed to read {}: {}", path.display(), e));
+    let mut keys = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match config::signature::decode_hex(line) {
+            Some(key) if key.len() == config::signature::PUBLIC_KEY_LENGTH => keys.push(key),
+            _ => panic!(
+                "signed-config: line {} of {} is not a public key in hex.",
+                n + 1,
+                path.display()
+            ),
+        }
+    }
+    if keys.is_empty() {
+        panic!("signed-config: no key in {}.", path.display());
+    }
+
+    let policy = match env::var("B2_SIGNATURE_POLICY").as_deref() {
+        Ok("refuse") | Err(_) => "Refuse",
+        Ok("embedded") => {
+            if env::var_os("CARGO_FEATURE_EMBEDDED_CONFIG").is_none() {
+                panic!("signed-config: B2_SIGNATURE_POLICY=embedded requires embedded-config.");
+            }
+            "Embedded"
+        }
+        Ok("warn") => "Warn",
+        Ok(k) => panic!(
+            "signed-config: unknown B2_SIGNATURE_POLICY {}, expected `refuse`, `embedded` or `warn`.",
+            k
+        ),
+    };
+    let out = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("config_keys.rs");
+    fs::write(
+        out,
+        format!(
+            "pub const CONFIG_KEYS: &[[u8; 32]] = &{:?};\n\
+             pub const SIGNATURE_POLICY: SignaturePolicy = SignaturePolicy::{};\n",
+            keys, policy
+        ),
+    )
+    .unwrap();
+}
@@ -3,26 +3,40 @@
 extern crate alloc;
 use core::fmt::Display;
 
-#[cfg(feature = "no_std")]
-use alloc::{borrow::ToOwned, string::String, vec, vec::Vec};
+#[cfg(not(feature = "std"))]
+use alloc::{borrow::ToOwned, collections::BTreeMap, format, string::String, vec, vec::Vec};
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
 
 use serde::{Deserialize, Serialize};
 
+use crate::bootconf::{BootOption, BootOptionKind};
+use crate::condition::Condition;
+
+/// Version of config schema this crate reads and writes.
+///
+/// Bump it when a field or variant is added, and add a migration to `b2ctl migrate`.
+pub const SCHEMA_VERSION: u32 = 7;
+
 /// The configuration of b2.
 /// If config is unavailable or broken, b2 will simply load the fallback menu.
 /// Config can be stored in EFI variable or files in %ESP on UEFI-based machines.
 /// Config can be as well saved in NVRAM, a partition or in the bootloader.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Config {
+    /// Schema version the config is written for. Configs without it are of version 0.
+    #[serde(default)]
+    pub version: u32,
     /// Alternative title.
     pub name: Option<String>,
     /// Message
     pub message: Option<String>,
     /// Menu items.
     pub items: Vec<BootItem>,
-    /// Default item, counts from 0.
+    /// Default item, counts from 0, or a path into submenus such as `[2, 0]`.
     /// If the value is not in valid range, then first item (with id 0) will be selected.
-    pub default: u32,
+    pub default: ItemPath,
     /// Auto-boot timeout, in seconds.
     ///
     /// Special values:
@@ -31,31 +45,315 @@ pub struct Config {
     pub timeout: Option<u32>,
     /// If this option is set, a password will be required for whole bootloader.
     pub password: Option<String>,
+    /// File names of drivers to load from `\EFI\b2\drivers\`, such as `ext4_x64.efi`.
+    ///
+    /// If unset, all drivers there are loaded. An empty list loads none.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub drivers: Option<Vec<String>>,
+    /// Variables substituted as `${name}` in cmdlines and paths of entries.
+    ///
+    /// Built-in variables are named with prefix `b2.`, and listed in `variables::BUILTINS`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variables: Option<BTreeMap<String, String>>,
+    /// Variants of every item taking a cmdline, such as a recovery mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profiles: Option<Vec<Profile>>,
+}
+
+impl Config {
+    /// Item at `path`, which is indexes of items through submenus.
+    pub fn item(&self, path: &[u32]) -> Option<&BootItem> {
+        let (last, submenus) = path.split_last()?;
+        let mut items = &self.items;
+        for &i in submenus {
+            match &items.get(i as usize)?.target {
+                BootTarget::Submenu { items: k, .. } => items = k,
+                _ => return None,
+            }
+        }
+        items.get(*last as usize)
+    }
+
+    /// Path of default item, or of the first item if default is out of range.
+    pub fn default_path(&self) -> Vec<u32> {
+        let path = self.default.indexes();
+        if self.item(path).is_some() {
+            path.to_vec()
+        } else {
+            vec![0]
+        }
+    }
+
+    /// Remove items, in submenus too, for which `keep` returns false.
+    ///
+    /// Default stays on the same item if it is kept, or is the first item otherwise.
+    pub fn retain_items(&mut self, keep: &mut dyn FnMut(&BootItem) -> bool) {
+        /// Returns new path of default item, if it is kept.
+        fn retain(
+            items: &mut Vec<BootItem>,
+            keep: &mut dyn FnMut(&BootItem) -> bool,
+            default: Option<&[u32]>,
+        ) -> Option<Vec<u32>> {
+            let mut ret = None;
+            for (n, mut item) in core::mem::take(items).into_iter().enumerate() {
+                if !keep(&item) {
+                    continue;
+                }
+                let index = items.len() as u32;
+                let inner = default
+                    .and_then(|x| x.split_first())
+                    .filter(|x| *x.0 == n as u32)
+                    .map(|x| x.1);
+                if inner == Some(&[]) {
+                    ret = Some(vec![index]);
+                }
+                if let BootTarget::Submenu { items, .. } = &mut item.target {
+                    let inner = inner.filter(|x| !x.is_empty());
+                    if let Some(mut path) = retain(items, keep, inner) {
+                        path.insert(0, index);
+                        ret = Some(path);
+                    }
+                }
+                items.push(item);
+            }
+            ret
+        }
+        let default = self.default_path();
+        self.default = match retain(&mut self.items, keep, Some(&default)) {
+            Some(path) if path.len() == 1 => ItemPath::Index(path[0]),
+            Some(path) => ItemPath::Path(path),
+            None => ItemPath::Index(0),
+        };
+    }
+
+    /// Append items of a fragment. Other settings are those of this config.
+    pub fn merge(&mut self, fragment: Fragment) {
+        self.items.extend(fragment.items);
+    }
+
+    /// Parts of config this version of b2 does not understand, which are ignored.
+    ///
+    /// Those are usually written for a newer b2.
+    pub fn compatibility_warnings(&self) -> Vec<String> {
+        let mut ret = Vec::new();
+        if self.version > SCHEMA_VERSION {
+            ret.push(format!(
+                "config is of schema version {}, newer than {} of this b2, unknown settings are ignored.",
+                self.version, SCHEMA_VERSION
+            ));
+        }
+        Self::item_warnings(&self.items, &mut ret);
+        ret
+    }
+
+    fn item_warnings(items: &[BootItem], ret: &mut Vec<String>) {
+        for item in items {
+            if item.conditions.iter().flatten().any(Condition::is_unknown) {
+                ret.push(format!(
+                    "entry {}: unknown condition type, the entry is hidden.",
+                    item.name
+                ));
+            }
+            if item.hotkey.is_some() && item.usable_hotkey().is_none() {
+                ret.push(format!(
+                    "entry {}: hotkey is used to select entries at the prompt, and is ignored.",
+                    item.name
+                ));
+            }
+            match &item.target {
+                BootTarget::Submenu { items, .. } => Self::item_warnings(items, ret),
+                BootTarget::Unknown => {
+                    ret.push(format!("entry {}: unknown target type.", item.name))
+                }
+                BootTarget::Linux {
+                    kernel,
+                    initrd,
+                    devicetree,
+                    ..
+                } if core::iter::once(kernel)
+                    .chain(initrd)
+                    .chain(devicetree)
+                    .any(|x| matches!(x, ImageLocation::Unknown)) =>
+                {
+                    ret.push(format!("entry {}: unknown image location type.", item.name))
+                }
+                BootTarget::Multiboot2 {
+                    kernel, modules, ..
+                } if core::iter::once(kernel)
+                    .chain(modules.iter().map(|x| &x.image))
+                    .any(|x| matches!(x, ImageLocation::Unknown)) =>
+                {
+                    ret.push(format!("entry {}: unknown image location type.", item.name))
+                }
+                _ => {}
+            }
+            for option in item.options.iter().flatten() {
+                if let BootOptionKind::Default = option.option {
+                    ret.push(format!(
+                        "entry {}: option {} is of unknown kind.",
+                        item.name, option.identifier
+                    ));
+                }
+            }
+        }
+    }
 }
 
+/// Items kept in a file of their own, and merged into config, so each OS installed can manage its own.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Fragment {
+    /// Schema version the fragment is written for, as `version` of config.
+    #[serde(default)]
+    pub version: u32,
+    pub items: Vec<BootItem>,
+}
+
+impl Fragment {
+    /// Parts of fragment this version of b2 does not understand, as those of config.
+    pub fn compatibility_warnings(&self) -> Vec<String> {
+        let mut ret = Vec::new();
+        if self.version > SCHEMA_VERSION {
+            ret.push(format!(
+                "fragment is of schema version {}, newer than {} of this b2, unknown settings are ignored.",
+                self.version, SCHEMA_VERSION
+            ));
+        }
+        Config::item_warnings(&self.items, &mut ret);
+        ret
+    }
+}
+
+/// An item in menu, by its index, or by a path of indexes through submenus.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+pub enum ItemPath {
+    /// Index of an item in top level menu.
+    Index(u32),
+    /// Indexes of submenus, then of the item in the last one.
+    Path(Vec<u32>),
+}
+
+impl ItemPath {
+    pub fn indexes(&self) -> &[u32] {
+        match self {
+            Self::Index(k) => core::slice::from_ref(k),
+            Self::Path(k) => k,
+        }
+    }
+}
+
+impl From<u32> for ItemPath {
+    fn from(value: u32) -> Self {
+        Self::Index(value)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct BootItem {
     pub name: String,
     pub target: BootTarget,
+    /// Boot options asked before booting this item.
+    ///
+    /// Selections are appended to cmdline at `pos` of each option.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<Vec<BootOption>>,
+    /// Key to boot this item from menu directly, such as `w`, `F12` or `Ctrl-r`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hotkey: Option<String>,
+    /// Conditions for this item to be shown, which must all hold.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conditions: Option<Vec<Condition>>,
+    /// Whether profiles of config apply to this item, which they do if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profiles: Option<bool>,
+}
+
+impl BootItem {
+    /// Create an item with no extra attributes.
+    pub fn new(name: &str, target: BootTarget) -> Self {
+        Self {
+            name: name.to_owned(),
+            target,
+            options: None,
+            hotkey: None,
+            conditions: None,
+            profiles: None,
+        }
+    }
+
+    /// Hotkey of this item, unless it is a key typed first at the `Boot:` prompt to select or
+    /// edit entries, which would take it over.
+    pub fn usable_hotkey(&self) -> Option<&str> {
+        self.hotkey
+            .as_deref()
+            .filter(|x| !matches!(x.as_bytes(), [b'e' | b'E' | b'0'..=b'9']))
+    }
+
+    /// Whether profiles apply to this item, which takes a cmdline and does not opt out.
+    pub fn takes_profiles(&self) -> bool {
+        self.profiles != Some(false) && self.target.cmdline().is_some()
+    }
+
+    /// This item booted with `profile`, or `None` if the profile does not apply to it.
+    pub fn with_profile(&self, profile: &Profile) -> Option<BootItem> {
+        if !self.takes_profiles() {
+            return None;
+        }
+        let cmdline = self.target.cmdline()?;
+        let mut ret = self.clone();
+        ret.target.set_cmdline(if cmdline.is_empty() {
+            profile.cmdline.clone()
+        } else {
+            format!("{} {}", cmdline, profile.cmdline)
+        });
+        Some(ret)
+    }
+}
+
+/// A variant of items, booted with more cmdline.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Profile {
+    /// Name shown in menu, such as `recovery`.
+    pub name: String,
+    /// Appended to cmdline of the item, such as `single`.
+    pub cmdline: String,
 }
 
 /// Boot Target - represents a bootable target.
 ///
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(tag = "type")]
 pub enum BootTarget {
     /// EFI image.
     #[serde(rename = "efi")]
     EFI {
+        /// Path of image, which may name its volume such as `PARTUUID=...:/vmlinuz`.
         path: String,
         cmdline: Option<String>,
     },
-    /// Linux image.
+    /// Linux image, booted through its EFI stub.
     #[serde(rename = "linux")]
     Linux {
         kernel: ImageLocation,
         initrd: Vec<ImageLocation>,
         cmdline: String,
+        /// Device tree given to the kernel instead of the one of firmware.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        devicetree: Option<ImageLocation>,
+    },
+    /// Multiboot2 kernel, such as Xen or a hobby OS, with its modules.
+    #[serde(rename = "multiboot2")]
+    Multiboot2 {
+        kernel: ImageLocation,
+        #[serde(default)]
+        cmdline: String,
+        #[serde(default)]
+        modules: Vec<Module>,
     },
     /// Display a message. Message should not be more than a page.
     #[serde(rename = "message")]
@@ -75,6 +373,20 @@ pub enum BootTarget {
     /// Show debug information.
     #[serde(rename = "debug")]
     Debug,
+    /// Rescue command shell.
+    #[serde(rename = "shell")]
+    Shell,
+    /// Browse files on all volumes, and boot one of them.
+    #[serde(rename = "file_browser")]
+    FileBrowser,
+    /// A menu of more items, such as older kernels and recovery modes of an OS.
+    #[serde(rename = "submenu")]
+    Submenu {
+        /// Title of the submenu. Name of the entry is used if unset.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        items: Vec<BootItem>,
+    },
     /// Nothing happens.
     #[serde(rename = "nop")]
     Nop,
@@ -84,14 +396,94 @@ pub enum BootTarget {
     /// Unknown.
     #[serde(rename = "unknown")]
     #[serde(other)]
+    #[cfg_attr(feature = "schema", schemars(skip))]
     Unknown,
 }
 
+impl BootTarget {
+    /// Command line passed to the target, if the target takes one.
+    ///
+    /// An EFI image without cmdline is reported as an empty one.
+    pub fn cmdline(&self) -> Option<&str> {
+        match self {
+            BootTarget::EFI { cmdline, .. } => Some(cmdline.as_deref().unwrap_or("")),
+            BootTarget::Linux { cmdline, .. } | BootTarget::Multiboot2 { cmdline, .. } => {
+                Some(cmdline.as_str())
+            }
+            _ => None,
+        }
+    }
+
+    /// Replace command line of the target.
+    ///
+    /// Returns `false` if the target does not take a command line.
+    pub fn set_cmdline(&mut self, value: String) -> bool {
+        match self {
+            BootTarget::EFI { cmdline, .. } => {
+                *cmdline = Some(value);
+                true
+            }
+            BootTarget::Linux { cmdline, .. } | BootTarget::Multiboot2 { cmdline, .. } => {
+                *cmdline = value;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Append `value` to the command line at `pos`, which is 0 for the target itself, and `n` for
+    /// the `n`th module of a multiboot2 kernel.
+    ///
+    /// Returns `false` if the target has no command line there.
+    pub fn append_cmdline(&mut self, pos: usize, value: &str) -> bool {
+        let append = |cmdline: &mut String| {
+            if !cmdline.is_empty() {
+                cmdline.push(' ');
+            }
+            cmdline.push_str(value);
+        };
+        match (self, pos) {
+            (BootTarget::EFI { cmdline, .. }, 0) => append(cmdline.get_or_insert_with(String::new)),
+            (BootTarget::Linux { cmdline, .. } | BootTarget::Multiboot2 { cmdline, .. }, 0) => {
+                append(cmdline)
+            }
+            (BootTarget::Multiboot2 { modules, .. }, n) => match modules.get_mut(n - 1) {
+                Some(module) => append(&mut module.cmdline),
+                None => return false,
+            },
+            _ => return false,
+        }
+        true
+    }
+
+    /// FNV-1a hash of the target in JSON, which tells targets apart even if their items share a
+    /// name, and stays the same across boots.
+    pub fn fingerprint(&self) -> u64 {
+        serde_json::to_vec(self)
+            .unwrap_or_default()
+            .iter()
+            .fold(0xcbf2_9ce4_8422_2325, |hash, x| {
+                (hash ^ u64::from(*x)).wrapping_mul(0x0100_0000_01b3)
+            })
+    }
+}
+
+/// Module loaded for a multiboot2 kernel, such as the dom0 kernel and initrd of Xen.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Module {
+    pub image: ImageLocation,
+    /// String passed with the module, which is usually its cmdline.
+    #[serde(default)]
+    pub cmdline: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(tag = "type")]
 pub enum ImageLocation {
-    /// A path in file system.
-    Path(String),
+    /// A path in file system, on the volume of b2 or a volume named by prefix such as `LABEL=boot:`.
+    Path { path: String },
     /// A whole partition as image.
     /// TODO: Auto-detection of single image and compound partition.
     Partition { disk: i32, part: i32 },
@@ -105,11 +497,217 @@ pub enum ImageLocation {
         segment: Extent,
     },
     #[serde(other)]
+    #[cfg_attr(feature = "schema", schemars(skip))]
     Unknown,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Extent {
     from: u32,
     length: u32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn newer_schema_warnings() {
+        let config = Config {
+            version: SCHEMA_VERSION + 1,
+            name: None,
+            message: None,
+            items: vec![
+                BootItem::new("Reboot", BootTarget::Reboot),
+                BootItem::new("Future", BootTarget::Unknown),
+            ],
+            default: 0.into(),
+            timeout: None,
+            password: None,
+            drivers: None,
+            variables: None,
+            profiles: None,
+        };
+        let warnings = config.compatibility_warnings();
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[1].contains("Future"));
+    }
+
+    #[test]
+    fn submenu_path() {
+        let text = r#"{"version": 2, "name": null, "message": null, "timeout": null, "password": null,
+            "default": [1, 0],
+            "items": [
+                {"name": "Reboot", "target": {"type": "reboot"}},
+                {"name": "Linux", "target": {"type": "submenu", "items": [
+                    {"name": "Shell", "target": {"type": "shell"}}
+                ]}}
+            ]}"#;
+        let mut config: Config = serde_json::from_str(text).unwrap();
+        assert_eq!(config.default_path(), [1, 0]);
+        assert_eq!(config.item(&[1, 0]).unwrap().name, "Shell");
+        assert!(config.item(&[0, 0]).is_none());
+        config.default = ItemPath::Path(vec![1, 1]);
+        assert_eq!(config.default_path(), [0]);
+        config.default = 1.into();
+        assert_eq!(config.default_path(), [1]);
+    }
+
+    #[test]
+    fn merge_fragment() {
+        let mut config: Config = crate::parse_config(
+            br#"{"items": [{"name": "Reboot", "target": {"type": "reboot"}}],
+            "default": 0, "name": "b2", "message": null, "timeout": 5, "password": null}"#,
+        )
+        .unwrap();
+        let fragment: Fragment = crate::parse::parse(
+            br#"{"version": 99, "timeout": 0, "items": [{"name": "Debian", "target": {"type": "nop"}}]}"#,
+        )
+        .unwrap();
+        assert_eq!(fragment.compatibility_warnings().len(), 1);
+        config.merge(fragment);
+        assert_eq!(config.items[1].name, "Debian");
+        assert_eq!(config.timeout, Some(5));
+    }
+
+    #[test]
+    fn profiles() {
+        let profile = Profile {
+            name: "recovery".into(),
+            cmdline: "single".into(),
+        };
+        let mut item = BootItem::new(
+            "Linux",
+            BootTarget::EFI {
+                path: "/vmlinuz".into(),
+                cmdline: None,
+            },
+        );
+        let recovery = item.with_profile(&profile).unwrap();
+        assert_eq!(recovery.target.cmdline(), Some("single"));
+        let recovery = recovery.with_profile(&profile).unwrap();
+        assert_eq!(recovery.target.cmdline(), Some("single single"));
+        assert_eq!(item.target.fingerprint(), item.clone().target.fingerprint());
+        assert_ne!(item.target.fingerprint(), recovery.target.fingerprint());
+        assert!(item.takes_profiles());
+        item.profiles = Some(false);
+        assert!(!item.takes_profiles());
+        assert!(item.with_profile(&profile).is_none());
+        assert!(BootItem::new("Reboot", BootTarget::Reboot)
+            .with_profile(&profile)
+            .is_none());
+    }
+
+    #[test]
+    fn reserved_hotkeys() {
+        let mut item = BootItem::new("Windows", BootTarget::Reboot);
+        for (hotkey, usable) in [
+            ("w", true),
+            ("F1", true),
+            ("e", false),
+            ("E", false),
+            ("1", false),
+        ] {
+            item.hotkey = Some(hotkey.into());
+            assert_eq!(item.usable_hotkey().is_some(), usable);
+            let mut warnings = Vec::new();
+            Config::item_warnings(core::slice::from_ref(&item), &mut warnings);
+            assert_eq!(warnings.is_empty(), usable);
+        }
+    }
+
+    #[test]
+    fn multiboot2_target() {
+        let target: BootTarget = serde_json::from_str(
+            r#"{"type": "multiboot2", "kernel": {"type": "Path", "path": "/xen.gz"},
+                "cmdline": "dom0_mem=2G", "modules": [
+                    {"image": {"type": "Path", "path": "/vmlinuz"}, "cmdline": "console=hvc0"},
+                    {"image": {"type": "Flash"}}
+                ]}"#,
+        )
+        .unwrap();
+        assert_eq!(target.cmdline(), Some("dom0_mem=2G"));
+        let BootTarget::Multiboot2 {
+            kernel, modules, ..
+        } = &target
+        else {
+            panic!()
+        };
+        assert!(matches!(kernel, ImageLocation::Path { path } if path == "/xen.gz"));
+        assert_eq!(modules[0].cmdline, "console=hvc0");
+        assert!(matches!(modules[1].image, ImageLocation::Unknown));
+        let mut target = target.clone();
+        assert!(target.append_cmdline(0, "noreboot"));
+        assert!(target.append_cmdline(2, "quiet"));
+        assert!(!target.append_cmdline(3, "quiet"));
+        assert_eq!(target.cmdline(), Some("dom0_mem=2G noreboot"));
+        let BootTarget::Multiboot2 { modules, .. } = &target else {
+            panic!()
+        };
+        assert_eq!(modules[1].cmdline, "quiet");
+        let mut warnings = Vec::new();
+        Config::item_warnings(&[BootItem::new("Xen", target)], &mut warnings);
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn linux_devicetree() {
+        let json = r#"{"type": "linux", "kernel": {"type": "Path", "path": "/Image"},
+            "initrd": [], "cmdline": "quiet"}"#;
+        let target: BootTarget = serde_json::from_str(json).unwrap();
+        assert!(matches!(
+            target,
+            BootTarget::Linux {
+                devicetree: None,
+                ..
+            }
+        ));
+        // Targets without a device tree are written as before.
+        assert!(!serde_json::to_string(&target)
+            .unwrap()
+            .contains("devicetree"));
+        let target: BootTarget = serde_json::from_str(
+            r#"{"type": "linux", "kernel": {"type": "Path", "path": "/Image"}, "initrd": [],
+                "cmdline": "", "devicetree": {"type": "Path", "path": "/board.dtb"}}"#,
+        )
+        .unwrap();
+        let BootTarget::Linux { devicetree, .. } = &target else {
+            panic!()
+        };
+        assert!(matches!(devicetree, Some(ImageLocation::Path { path }) if path == "/board.dtb"));
+    }
+
+    #[test]
+    fn retain_default() {
+        let submenu = |items| BootTarget::Submenu { name: None, items };
+        let mut config = Config {
+            version: SCHEMA_VERSION,
+            name: None,
+            message: None,
+            items: vec![
+                BootItem::new("Hidden", BootTarget::Nop),
+                BootItem::new(
+                    "Linux",
+                    submenu(vec![
+                        BootItem::new("Hidden", BootTarget::Nop),
+                        BootItem::new("Shell", BootTarget::Shell),
+                    ]),
+                ),
+            ],
+            default: ItemPath::Path(vec![1, 1]),
+            timeout: None,
+            password: None,
+            drivers: None,
+            variables: None,
+            profiles: None,
+        };
+        config.retain_items(&mut |x| x.name != "Hidden");
+        assert_eq!(config.default, ItemPath::Path(vec![0, 0]));
+        assert_eq!(config.item(&[0, 0]).unwrap().name, "Shell");
+        config.default = 0.into();
+        config.retain_items(&mut |x| x.name != "Shell");
+        assert_eq!(config.default, ItemPath::Index(0));
+        assert!(config.item(&[0, 0]).is_none());
+    }
+}
@@ -2,16 +2,17 @@
 //!
 //! This can be used to compose boot options with an user-friendly interface.
 
-#[cfg(feature = "no_std")]
+#[cfg(not(feature = "std"))]
 extern crate alloc;
-#[cfg(feature = "no_std")]
-use alloc::{string::String, vec, vec::Vec};
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
 use serde::{Deserialize, Serialize};
 
 /// User-selectable boot option.
 ///
 /// No dependency check here.
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct BootOption {
     /// Short option identifier.
     ///
@@ -19,7 +20,8 @@ pub struct BootOption {
     pub identifier: String,
     /// The cmdline to append to.
     ///
-    /// Useful with multiboot where every module have its own cmdline.
+    /// `0` is the cmdline of the entry, and `n` that of the `n`th module of a multiboot2 entry,
+    /// as every module has its own cmdline.
     pub pos: i32,
     /// Human readable name
     pub name: String,
@@ -29,6 +31,7 @@ pub struct BootOption {
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum BootOptionKind {
     /// Yes/No option.
     ///
@@ -44,10 +47,12 @@ pub enum BootOptionKind {
     Template(String),
     /// Unknown, for compability
     #[serde(other)]
+    #[cfg_attr(feature = "schema", schemars(skip))]
     Default,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct BootOptionItem {
     pub identifier: String,
     pub name: String,
@@ -56,6 +61,7 @@ pub struct BootOptionItem {
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(tag = "type")]
 pub enum BootOptionSelection {
     Bool(bool),
@@ -0,0 +1,250 @@
+//! Conditions of menu entries, checked on the machine b2 runs on.
+//!
+//! One config can be shared by many machines, with entries shown only where they apply.
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, format, string::String, vec::Vec};
+use serde::{Deserialize, Serialize};
+
+/// A condition for an entry to be shown.
+///
+/// Patterns match whole text ignoring case, where `*` matches any text and `?` any char.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "type")]
+pub enum Condition {
+    /// A file exists, at a path which may name its volume such as `LABEL=data:/windows.flag`.
+    #[serde(rename = "file_exists")]
+    FileExists { path: String },
+    /// b2 runs on a CPU architecture, such as `x86_64` or `aarch64`.
+    #[serde(rename = "arch")]
+    Arch { arch: String },
+    /// Secure Boot is enabled, or disabled if `enabled` is false.
+    #[serde(rename = "secure_boot")]
+    SecureBoot { enabled: bool },
+    /// Secure Boot is in setup mode, with no platform key enrolled, or not if `enabled` is false.
+    #[serde(rename = "setup_mode")]
+    SetupMode { enabled: bool },
+    /// System vendor and product name in SMBIOS match patterns. Unset ones match anything.
+    #[serde(rename = "smbios")]
+    Smbios {
+        vendor: Option<String>,
+        product: Option<String>,
+    },
+    /// An EFI variable exists, and its content matches `value` if set.
+    ///
+    /// `vendor` is a GUID, or `global` for variables of UEFI spec. Variables of b2 are read if unset.
+    /// Content is matched as text, or in hex such as `01` if it is not text.
+    #[serde(rename = "variable")]
+    Variable {
+        name: String,
+        vendor: Option<String>,
+        value: Option<String>,
+    },
+    /// Firmware vendor matches a pattern, such as `American Megatrends*`.
+    #[serde(rename = "firmware_vendor")]
+    FirmwareVendor { vendor: String },
+    /// A condition does not hold.
+    #[serde(rename = "not")]
+    Not { condition: Box<Condition> },
+    /// Unknown, which never holds.
+    #[serde(other)]
+    #[cfg_attr(feature = "schema", schemars(skip))]
+    Unknown,
+}
+
+/// Facts about the machine b2 runs on.
+///
+/// Facts that cannot be found out are `None`, and conditions on them do not hold.
+pub trait Machine {
+    /// CPU architecture, in names of Rust targets such as `x86_64`.
+    fn arch(&self) -> &str;
+    fn file_exists(&mut self, path: &str) -> bool;
+    fn secure_boot(&mut self) -> Option<bool>;
+    fn setup_mode(&mut self) -> Option<bool>;
+    /// System vendor and product name in SMBIOS.
+    fn smbios(&mut self) -> Option<(String, String)>;
+    /// Content of an EFI variable, with vendor as in `Condition::Variable`.
+    fn variable(&mut self, name: &str, vendor: Option<&str>) -> Option<Vec<u8>>;
+    fn firmware_vendor(&mut self) -> Option<String>;
+}
+
+impl Condition {
+    /// Whether the condition holds on `machine`.
+    pub fn holds(&self, machine: &mut dyn Machine) -> bool {
+        match self {
+            Self::FileExists { path } => machine.file_exists(path),
+            Self::Arch { arch } => machine.arch().eq_ignore_ascii_case(arch),
+            Self::SecureBoot { enabled } => machine.secure_boot() == Some(*enabled),
+            Self::SetupMode { enabled } => machine.setup_mode() == Some(*enabled),
+            Self::Smbios { vendor, product } => match machine.smbios() {
+                Some((v, p)) => optional_matches(vendor, &v) && optional_matches(product, &p),
+                None => false,
+            },
+            Self::Variable {
+                name,
+                vendor,
+                value,
+            } => match machine.variable(name, vendor.as_deref()) {
+                Some(data) => optional_matches(value, &variable_text(&data)),
+                None => false,
+            },
+            Self::FirmwareVendor { vendor } => machine
+                .firmware_vendor()
+                .is_some_and(|x| pattern_matches(vendor, &x)),
+            Self::Not { condition } => !condition.holds(machine),
+            Self::Unknown => false,
+        }
+    }
+
+    /// Whether the condition or one inside it is of an unknown type.
+    pub fn is_unknown(&self) -> bool {
+        match self {
+            Self::Not { condition } => condition.is_unknown(),
+            Self::Unknown => true,
+            _ => false,
+        }
+    }
+}
+
+/// Content of a variable as text, or in hex if it is not text.
+///
+/// Text is in UTF-8 or UCS-2, and may end with NUL.
+pub fn variable_text(data: &[u8]) -> String {
+    let printable = |x: &str| !x.is_empty() && !x.chars().any(char::is_control);
+    if let Ok(text) = core::str::from_utf8(data) {
+        let text = text.trim_end_matches('\0');
+        if printable(text) {
+            return text.into();
+        }
+    }
+    if data.len() % 2 == 0 {
+        let wide = data.chunks(2).map(|x| u16::from_le_bytes([x[0], x[1]]));
+        if let Ok(text) = char::decode_utf16(wide).collect::<Result<String, _>>() {
+            let text = text.trim_end_matches('\0');
+            if printable(text) {
+                return text.into();
+            }
+        }
+    }
+    data.iter().map(|x| format!("{:02x}", x)).collect()
+}
+
+/// Match `text` with a pattern, which matches anything if unset.
+fn optional_matches(pattern: &Option<String>, text: &str) -> bool {
+    match pattern {
+        Some(pattern) => pattern_matches(pattern, text),
+        None => true,
+    }
+}
+
+/// Match whole `text` with `pattern` ignoring case, where `*` matches any text and `?` any char.
+pub fn pattern_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().flat_map(char::to_lowercase).collect();
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    // Position after the last `*`, and text position it is tried at.
+    let mut star = None;
+    let (mut p, mut t) = (0, 0);
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                // Let the last `*` take one more char.
+                Some((sp, st)) => {
+                    star = Some((sp, st + 1));
+                    p = sp;
+                    t = st + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&x| x == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(not(feature = "std"))]
+    use alloc::vec;
+
+    struct TestMachine;
+
+    impl Machine for TestMachine {
+        fn arch(&self) -> &str {
+            "x86_64"
+        }
+        fn file_exists(&mut self, path: &str) -> bool {
+            path == "/vmlinuz"
+        }
+        fn secure_boot(&mut self) -> Option<bool> {
+            Some(true)
+        }
+        fn setup_mode(&mut self) -> Option<bool> {
+            None
+        }
+        fn smbios(&mut self) -> Option<(String, String)> {
+            Some(("LENOVO".into(), "ThinkPad X1 Carbon".into()))
+        }
+        fn variable(&mut self, name: &str, vendor: Option<&str>) -> Option<Vec<u8>> {
+            match (name, vendor) {
+                ("SecureBoot", Some("global")) => Some(vec![1]),
+                ("Role", None) => Some(b"kiosk\0".to_vec()),
+                _ => None,
+            }
+        }
+        fn firmware_vendor(&mut self) -> Option<String> {
+            Some("EDK II".into())
+        }
+    }
+
+    #[test]
+    fn patterns() {
+        assert!(pattern_matches("thinkpad*", "ThinkPad X1 Carbon"));
+        assert!(pattern_matches("*x1*", "ThinkPad X1 Carbon"));
+        assert!(pattern_matches("EDK?II", "EDK II"));
+        assert!(pattern_matches("a*b*c", "aXbYbZc"));
+        assert!(!pattern_matches("a*b", "aXbYc"));
+        assert!(!pattern_matches("EDK", "EDK II"));
+        assert!(pattern_matches("*", ""));
+    }
+
+    #[test]
+    fn conditions() {
+        let text = r#"[
+            {"type": "arch", "arch": "X86_64"},
+            {"type": "file_exists", "path": "/vmlinuz"},
+            {"type": "secure_boot", "enabled": true},
+            {"type": "smbios", "product": "ThinkPad*"},
+            {"type": "variable", "name": "SecureBoot", "vendor": "global", "value": "01"},
+            {"type": "variable", "name": "Role", "value": "kiosk"},
+            {"type": "firmware_vendor", "vendor": "edk*"},
+            {"type": "not", "condition": {"type": "setup_mode", "enabled": false}}
+        ]"#;
+        let conditions: Vec<Condition> = serde_json::from_str(text).unwrap();
+        for i in &conditions {
+            assert!(i.holds(&mut TestMachine), "{:?}", i);
+        }
+        let text = r#"[
+            {"type": "arch", "arch": "aarch64"},
+            {"type": "smbios", "vendor": "Dell*", "product": "ThinkPad*"},
+            {"type": "variable", "name": "Missing"},
+            {"type": "setup_mode", "enabled": false},
+            {"type": "tpm"}
+        ]"#;
+        let conditions: Vec<Condition> = serde_json::from_str(text).unwrap();
+        for i in &conditions {
+            assert!(!i.holds(&mut TestMachine), "{:?}", i);
+        }
+        assert!(conditions[4].is_unknown());
+    }
+}
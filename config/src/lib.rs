@@ -1,7 +1,18 @@
-#![cfg_attr(feature = "no_std", no_std)]
+#![cfg_attr(not(feature = "std"), no_std)]
 
 pub mod boot;
 pub mod bootconf;
+pub mod condition;
+pub mod parse;
+#[cfg(feature = "schema")]
+pub mod schema;
+pub mod signature;
+pub mod source;
+pub mod variables;
 
-pub use boot::{BootItem, BootTarget, Config, Extent, ImageLocation};
+pub use boot::{
+    BootItem, BootTarget, Config, Extent, Fragment, ImageLocation, ItemPath, Module, Profile,
+    SCHEMA_VERSION,
+};
 pub use bootconf::{BootOption, BootOptionItem, BootOptionKind, BootOptionSelection};
+pub use parse::{parse_config, ParseError};
@@ -0,0 +1,138 @@
+//! Parsing config text, with errors pointing at where the text is wrong.
+//!
+//! Shared by b2 and `b2ctl check`, so both report an error the same way.
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+#[cfg(not(feature = "std"))]
+use alloc::{borrow::ToOwned, format, string::String};
+use core::fmt::{self, Display};
+use serde::de::DeserializeOwned;
+
+use crate::Config;
+
+/// Where and why config text fails to parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    /// Line, counting from 1. 0 if the error is not about a place in text.
+    pub line: usize,
+    /// Column in bytes, counting from 1, as serde_json reports it.
+    pub column: usize,
+    /// Byte offset in text.
+    pub offset: usize,
+    /// Text of the line, without line break.
+    pub excerpt: String,
+}
+
+impl ParseError {
+    /// Locate an error reported at `line` and `column` of `text`.
+    pub fn new(text: &[u8], line: usize, column: usize, message: &str) -> Self {
+        let text = String::from_utf8_lossy(text);
+        let (offset, excerpt) = match text.split('\n').enumerate().nth(line.wrapping_sub(1)) {
+            Some((n, content)) => {
+                let start: usize = text.split('\n').take(n).map(|x| x.len() + 1).sum();
+                let prefix = column.saturating_sub(1).min(content.len());
+                (start + prefix, content.trim_end_matches('\r').to_owned())
+            }
+            None => (text.len(), String::new()),
+        };
+        Self {
+            message: message.to_owned(),
+            line,
+            column,
+            offset,
+            excerpt,
+        }
+    }
+
+    /// The first line of report, without excerpt.
+    pub fn summary(&self) -> String {
+        if self.line == 0 {
+            return self.message.clone();
+        }
+        format!(
+            "line {}, column {} (byte {}): {}",
+            self.line, self.column, self.offset, self.message
+        )
+    }
+}
+
+impl Display for ParseError {
+    /// Summary, then the line with a caret under the column.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.summary())?;
+        if self.line == 0 {
+            return Ok(());
+        }
+        let number = format!("{}", self.line);
+        writeln!(f)?;
+        writeln!(f, "{} | {}", number, self.excerpt)?;
+        // Column is in bytes, and the caret is put after the chars before it.
+        let mut end = self.column.saturating_sub(1).min(self.excerpt.len());
+        while !self.excerpt.is_char_boundary(end) {
+            end -= 1;
+        }
+        // Tabs are kept, so the caret lines up with them.
+        let pad: String = self.excerpt[..end]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        write!(f, "{} | {}^", " ".repeat(number.len()), pad)
+    }
+}
+
+/// Parse config in JSON.
+pub fn parse_config(text: &[u8]) -> Result<Config, ParseError> {
+    parse(text)
+}
+
+/// Parse a part of config, such as a `Fragment`, in JSON.
+pub fn parse<T: DeserializeOwned>(text: &[u8]) -> Result<T, ParseError> {
+    serde_json::from_slice(text).map_err(|e| {
+        // Message of serde_json ends with the location, which is reported separately.
+        let message = format!("{}", e);
+        let message = match message.rfind(" at line ") {
+            Some(k) if e.line() != 0 => &message[..k],
+            _ => message.as_str(),
+        };
+        ParseError::new(text, e.line(), e.column(), message)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_location() {
+        let text = "{\n  \"name\": \"b2\",\n  \"items\": [] ]\n}";
+        let e = parse_config(text.as_bytes()).unwrap_err();
+        assert_eq!((e.line, e.column), (3, 15));
+        assert_eq!(e.offset, text.find(" ]").unwrap() + 1);
+        assert_eq!(e.excerpt, "  \"items\": [] ]");
+        let report = format!("{}", e);
+        assert!(report.ends_with("3 |   \"items\": [] ]\n  |               ^"));
+    }
+
+    #[test]
+    fn error_after_non_ascii() {
+        let text = "{\n  \"name\": \"引导\" ]\n}";
+        let e = parse_config(text.as_bytes()).unwrap_err();
+        // Column counts the 3 bytes of each char, and the caret the char itself.
+        assert_eq!((e.line, e.column), (2, 20));
+        assert_eq!(e.offset, text.find(" ]").unwrap() + 1);
+        let report = format!("{}", e);
+        let caret = format!("\n  | {}^", " ".repeat(15));
+        assert!(report.ends_with(&caret));
+    }
+
+    #[test]
+    fn escaped_strings() {
+        let text = r#"{"name": "b2 \"loader\"", "message": "C:\\EFI \u00e9",
+            "items": [], "default": 0, "timeout": null, "password": null}"#;
+        let config = parse_config(text.as_bytes()).unwrap();
+        assert_eq!(config.name.as_deref(), Some("b2 \"loader\""));
+        assert_eq!(config.message.as_deref(), Some("C:\\EFI \u{e9}"));
+    }
+}
@@ -0,0 +1,44 @@
+//! JSON Schema of config, generated from the types b2 parses, for editors to check `b2.conf`.
+
+use schemars::gen::SchemaSettings;
+use schemars::schema::RootSchema;
+
+use crate::{Config, SCHEMA_VERSION};
+
+/// Schema of config, with doc comments of the types as descriptions.
+///
+/// Variants for unknown types are left out: b2 ignores them, but they are mistakes while editing.
+pub fn schema() -> RootSchema {
+    let mut schema = SchemaSettings::draft07()
+        .into_generator()
+        .into_root_schema_for::<Config>();
+    schema.schema.metadata().title = Some(format!("b2 config, schema version {}", SCHEMA_VERSION));
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tagged_types() {
+        let schema = serde_json::to_value(schema()).unwrap();
+        let types = |name: &str| -> Vec<String> {
+            schema["definitions"][name]["oneOf"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|x| {
+                    x["properties"]["type"]["enum"][0]
+                        .as_str()
+                        .unwrap()
+                        .to_owned()
+                })
+                .collect()
+        };
+        let targets = types("BootTarget");
+        assert!(targets.iter().any(|x| x == "linux"));
+        assert!(!targets.iter().any(|x| x == "unknown"));
+        assert_eq!(types("ImageLocation"), ["Path", "Partition", "Segments"]);
+    }
+}
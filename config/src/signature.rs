@@ -0,0 +1,60 @@
+//! Format of config signatures, shared by b2 and b2ctl.
+//!
+//! A config is signed with ed25519. Its signature is put next to it, in a file named after the config
+//! with `.sig` appended, or in `ConfigSig` variable for `Config` variable.
+//! Keys and signatures are written in hex, so they can be copied around as text.
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+
+/// Appended to file name of a config to get its signature.
+pub const SIGNATURE_SUFFIX: &str = ".sig";
+
+/// EFI variable with signature of `Config` variable.
+pub const SIGNATURE_VARIABLE: &str = "ConfigSig";
+
+/// Length of a public key.
+pub const PUBLIC_KEY_LENGTH: usize = 32;
+
+/// Length of a signature.
+pub const SIGNATURE_LENGTH: usize = 64;
+
+pub fn encode_hex(data: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut ret = String::with_capacity(data.len() * 2);
+    for i in data {
+        ret.push(DIGITS[(i >> 4) as usize] as char);
+        ret.push(DIGITS[(i & 0xf) as usize] as char);
+    }
+    ret
+}
+
+/// Decode hex, ignoring surrounding whitespace.
+///
+/// Returns `None` for odd length or invalid digits.
+pub fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    let s = s.trim().as_bytes();
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    let digit = |c: u8| (c as char).to_digit(16).map(|x| x as u8);
+    s.chunks(2)
+        .map(|x| Some(digit(x[0])? << 4 | digit(x[1])?))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_round_trip() {
+        let data = [0x00, 0x7f, 0xa5, 0xff];
+        assert_eq!(encode_hex(&data), "007fa5ff");
+        assert_eq!(decode_hex(" 007FA5ff\n").unwrap(), data);
+        assert!(decode_hex("abc").is_none());
+        assert!(decode_hex("zz").is_none());
+    }
+}
@@ -0,0 +1,158 @@
+//! Where b2 loads config from, and the report of sources tried.
+//!
+//! Sources are tried in order, and the first valid config is used.
+//! Every source tried is recorded in a `LoadReport`, so users can tell why a config was not picked up.
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+#[cfg(not(feature = "std"))]
+use alloc::{borrow::ToOwned, format, string::String, vec::Vec};
+
+use crate::ParseError;
+
+/// File name of config on a volume.
+pub const CONFIG_FILE: &str = "b2.conf";
+
+/// Label of a volume holding config at its root, for configs kept off ESP.
+pub const CONFIG_VOLUME_LABEL: &str = "B2";
+
+/// Directory of fragments, next to config file, whose `*.json` are merged into config.
+pub const FRAGMENT_DIR: &str = "b2.conf.d";
+
+/// A source of config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigSource {
+    /// Config compiled into b2.
+    Embedded,
+    /// `b2.conf` next to b2 image, or at root of its volume.
+    File,
+    /// `Config` EFI variable.
+    Variable,
+    /// `b2.conf` at root of the volume labelled `CONFIG_VOLUME_LABEL`.
+    Label,
+}
+
+impl ConfigSource {
+    /// Sources tried without a `ConfigOrder` variable.
+    pub const DEFAULT_ORDER: &'static [ConfigSource] = &[
+        ConfigSource::File,
+        ConfigSource::Variable,
+        ConfigSource::Label,
+        ConfigSource::Embedded,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Embedded => "embedded",
+            Self::File => "file",
+            Self::Variable => "variable",
+            Self::Label => "label",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Self::Embedded, Self::File, Self::Variable, Self::Label]
+            .into_iter()
+            .find(|x| x.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Parse a list of sources separated by `,` or whitespace, such as `variable,file`.
+    ///
+    /// Sources listed twice are tried once.
+    pub fn parse_order(s: &str) -> Result<Vec<Self>, String> {
+        let mut ret = Vec::new();
+        for i in s.split(|c: char| c == ',' || c.is_whitespace()) {
+            if i.is_empty() {
+                continue;
+            }
+            let source =
+                Self::from_name(i).ok_or_else(|| format!("unknown config source {}.", i))?;
+            if !ret.contains(&source) {
+                ret.push(source);
+            }
+        }
+        if ret.is_empty() {
+            return Err("no config source.".to_owned());
+        }
+        Ok(ret)
+    }
+}
+
+/// Sources tried, and the one used.
+#[derive(Debug, Clone, Default)]
+pub struct LoadReport {
+    /// Source used and where it was found, or `None` if fallback menu is used.
+    pub used: Option<(ConfigSource, String)>,
+    /// Locations tried before, with the reason each was rejected.
+    pub rejected: Vec<(ConfigSource, String, String)>,
+    /// Fragments merged into the config used, in order.
+    pub fragments: Vec<String>,
+    /// Problems of the config used, which did not stop it from being used.
+    pub warnings: Vec<String>,
+    /// Configs read but not parsed, with where parsing stopped and the line there.
+    pub parse_errors: Vec<(String, ParseError)>,
+}
+
+impl LoadReport {
+    pub fn reject(&mut self, source: ConfigSource, location: &str, reason: &str) {
+        self.rejected
+            .push((source, location.to_owned(), reason.to_owned()));
+    }
+
+    /// One line per source tried, in order.
+    pub fn lines(&self) -> Vec<String> {
+        let mut ret: Vec<String> = self
+            .rejected
+            .iter()
+            .map(|(source, location, reason)| {
+                format!("skipped {} ({}): {}", location, source.name(), reason)
+            })
+            .collect();
+        ret.push(match &self.used {
+            Some((source, location)) => format!("using {} ({})", location, source.name()),
+            None => "no config found, using fallback menu".to_owned(),
+        });
+        ret.extend(self.fragments.iter().map(|x| format!("merged {}", x)));
+        ret.extend(self.warnings.iter().map(|x| format!("warning: {}", x)));
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(not(feature = "std"))]
+    use alloc::vec;
+
+    #[test]
+    fn source_order() {
+        assert_eq!(
+            ConfigSource::parse_order("variable, FILE file").unwrap(),
+            [ConfigSource::Variable, ConfigSource::File]
+        );
+        assert!(ConfigSource::parse_order("file,esp").is_err());
+        assert!(ConfigSource::parse_order(" ,").is_err());
+    }
+
+    #[test]
+    fn report_lines() {
+        let mut report = LoadReport::default();
+        report.reject(ConfigSource::File, "\\b2.conf", "not found.");
+        assert_eq!(
+            report.lines(),
+            [
+                "skipped \\b2.conf (file): not found.",
+                "no config found, using fallback menu"
+            ]
+        );
+        report.used = Some((ConfigSource::Variable, "Config variable".to_owned()));
+        report.fragments = vec!["\\b2.conf.d\\a.json".to_owned()];
+        assert_eq!(
+            report.lines()[1..],
+            [
+                "using Config variable (variable)",
+                "merged \\b2.conf.d\\a.json"
+            ]
+        );
+    }
+}
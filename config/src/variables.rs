@@ -0,0 +1,248 @@
+//! Variables of config, substituted as `${name}` in cmdlines and paths when booting.
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+#[cfg(not(feature = "std"))]
+use alloc::{format, string::String, vec::Vec};
+
+use crate::{BootTarget, Config, ImageLocation};
+
+/// Prefix of built-in variables, which are resolved by b2 when booting.
+pub const BUILTIN_PREFIX: &str = "b2.";
+
+/// Built-in variables.
+pub const BUILTINS: &[&str] = &[
+    // PARTUUID of the volume b2 is loaded from.
+    "b2.esp_partuuid",
+    // Name of the entry booted.
+    "b2.entry",
+    // Times b2 has booted an entry, this time included.
+    "b2.boot_count",
+    // Serial number of the system in SMBIOS.
+    "b2.smbios_serial",
+];
+
+/// Variables in values of variables are substituted up to this depth, so loops are found.
+const MAX_DEPTH: usize = 8;
+
+/// Replace `${name}` in `text` with what `lookup` gives for `name`. `$$` is a `$`.
+pub fn substitute(
+    text: &str,
+    lookup: &mut dyn FnMut(&str) -> Result<String, String>,
+) -> Result<String, String> {
+    let mut ret = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(k) = rest.find('$') {
+        ret.push_str(&rest[..k]);
+        rest = &rest[k + 1..];
+        if let Some(after) = rest.strip_prefix('$') {
+            ret.push('$');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('{') {
+            let end = after
+                .find('}')
+                .ok_or_else(|| format!("unterminated variable in {}", text))?;
+            ret.push_str(&lookup(&after[..end])?);
+            rest = &after[end + 1..];
+        } else {
+            ret.push('$');
+        }
+    }
+    ret.push_str(rest);
+    Ok(ret)
+}
+
+impl BootTarget {
+    /// Strings of the target in which variables are substituted: paths and cmdline.
+    pub fn strings_mut(&mut self) -> Vec<&mut String> {
+        match self {
+            BootTarget::EFI { path, cmdline } => {
+                let mut ret = Vec::from([path]);
+                ret.extend(cmdline.as_mut());
+                ret
+            }
+            BootTarget::Linux {
+                kernel,
+                initrd,
+                cmdline,
+                devicetree,
+            } => {
+                let mut ret: Vec<&mut String> = core::iter::once(kernel)
+                    .chain(initrd.iter_mut())
+                    .chain(devicetree.as_mut())
+                    .filter_map(|x| match x {
+                        ImageLocation::Path { path } => Some(path),
+                        _ => None,
+                    })
+                    .collect();
+                ret.push(cmdline);
+                ret
+            }
+            BootTarget::Multiboot2 {
+                kernel,
+                cmdline,
+                modules,
+            } => {
+                let mut ret = Vec::new();
+                if let ImageLocation::Path { path } = kernel {
+                    ret.push(path);
+                }
+                ret.push(cmdline);
+                for module in modules {
+                    if let ImageLocation::Path { path } = &mut module.image {
+                        ret.push(path);
+                    }
+                    ret.push(&mut module.cmdline);
+                }
+                ret
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Substitute variables of config, and built-ins from `builtin`, in `text`.
+    pub fn expand(
+        &self,
+        text: &str,
+        builtin: &mut dyn FnMut(&str) -> Option<String>,
+    ) -> Result<String, String> {
+        self.expand_at(text, builtin, 0)
+    }
+
+    fn expand_at(
+        &self,
+        text: &str,
+        builtin: &mut dyn FnMut(&str) -> Option<String>,
+        depth: usize,
+    ) -> Result<String, String> {
+        if depth > MAX_DEPTH {
+            return Err(format!("variables nested too deep in {}", text));
+        }
+        substitute(text, &mut |name| {
+            if name.starts_with(BUILTIN_PREFIX) {
+                return builtin(name)
+                    .ok_or_else(|| format!("built-in variable {} is unavailable", name));
+            }
+            match self.variables.as_ref().and_then(|x| x.get(name)) {
+                Some(value) => self.expand_at(value, builtin, depth + 1),
+                None => Err(format!("variable {} is not defined", name)),
+            }
+        })
+    }
+
+    /// Substitute variables in cmdline and paths of `target`.
+    pub fn expand_target(
+        &self,
+        target: &BootTarget,
+        builtin: &mut dyn FnMut(&str) -> Option<String>,
+    ) -> Result<BootTarget, String> {
+        let mut ret = target.clone();
+        for i in ret.strings_mut() {
+            *i = self.expand(i, builtin)?;
+        }
+        Ok(ret)
+    }
+
+    /// Variables which cannot be substituted in entries, as user defined or built-in.
+    pub fn variable_errors(&self) -> Vec<String> {
+        fn check(config: &Config, items: &[crate::BootItem], ret: &mut Vec<String>) {
+            for item in items {
+                if let BootTarget::Submenu { items, .. } = &item.target {
+                    check(config, items, ret);
+                }
+                let mut builtin = |x: &str| BUILTINS.contains(&x).then(String::new);
+                if let Err(e) = config.expand_target(&item.target, &mut builtin) {
+                    ret.push(format!("entry {}: {}.", item.name, e));
+                }
+            }
+        }
+        let mut ret = Vec::new();
+        check(self, &self.items, &mut ret);
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BootItem;
+    #[cfg(not(feature = "std"))]
+    use alloc::{borrow::ToOwned, string::ToString};
+
+    fn config(variables: &[(&str, &str)]) -> Config {
+        let mut config: Config = serde_json::from_str(
+            r#"{"items": [], "default": 0,
+            "name": null, "message": null, "timeout": null, "password": null}"#,
+        )
+        .unwrap();
+        config.variables = Some(
+            variables
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        );
+        config
+    }
+
+    #[test]
+    fn expand() {
+        let config = config(&[
+            ("root", "PARTUUID=${b2.esp_partuuid}"),
+            ("console", "console=ttyS0"),
+            ("loop", "${loop}"),
+        ]);
+        let mut builtin = |x: &str| (x == "b2.esp_partuuid").then(|| "1234".to_owned());
+        assert_eq!(
+            config.expand("root=${root} ${console} $$HOME $x", &mut builtin),
+            Ok("root=PARTUUID=1234 console=ttyS0 $HOME $x".to_owned())
+        );
+        assert!(config.expand("${missing}", &mut builtin).is_err());
+        assert!(config.expand("${b2.entry}", &mut builtin).is_err());
+        assert!(config.expand("${root", &mut builtin).is_err());
+        assert!(config.expand("${loop}", &mut builtin).is_err());
+    }
+
+    #[test]
+    fn expand_target() {
+        let mut config = config(&[("kernel", "/vmlinuz-6.1")]);
+        config.items.push(BootItem::new(
+            "Linux",
+            BootTarget::Linux {
+                kernel: ImageLocation::Path {
+                    path: "${kernel}".into(),
+                },
+                initrd: Vec::from([ImageLocation::Path {
+                    path: "/initrd-${b2.entry}".into(),
+                }]),
+                cmdline: "${undefined}".into(),
+                devicetree: None,
+            },
+        ));
+        let errors = config.variable_errors();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("undefined"));
+        config
+            .variables
+            .as_mut()
+            .unwrap()
+            .insert("undefined".into(), "quiet".into());
+        assert!(config.variable_errors().is_empty());
+        let mut builtin = |_: &str| Some("Linux".to_owned());
+        let BootTarget::Linux {
+            kernel: ImageLocation::Path { path: kernel },
+            initrd,
+            cmdline,
+            ..
+        } = config
+            .expand_target(&config.items[0].target, &mut builtin)
+            .unwrap()
+        else {
+            panic!()
+        };
+        assert_eq!(kernel, "/vmlinuz-6.1");
+        assert!(matches!(&initrd[0], ImageLocation::Path { path } if path == "/initrd-Linux"));
+        assert_eq!(cmdline, "quiet");
+    }
+}
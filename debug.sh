@@ -1,4 +1,14 @@
 #!/bin/sh
+# Run with ARCH=aarch64 to debug on qemu-system-aarch64 with AAVMF.
+case "${ARCH:-x86_64}" in
+aarch64)
+cargo b --target=aarch64-unknown-uefi -p b2 && \
+cp target/aarch64-unknown-uefi/debug/b2.efi esp/efi/boot/bootaa64.efi && \
+qemu-system-aarch64 -M virt -cpu cortex-a57 -drive if=pflash,format=raw,readonly=on,file=/usr/share/edk2/aarch64/QEMU_CODE.fd -drive if=pflash,format=raw,readonly=on,file=/usr/share/edk2/aarch64/QEMU_VARS.fd -drive format=raw,file=fat:rw:esp -device ramfb -device qemu-xhci -device usb-kbd -m 1024M
+;;
+*)
 cargo b --target=x86_64-unknown-uefi -p b2 && \
 cp target/x86_64-unknown-uefi/debug/b2.efi esp/efi/boot/bootx64.efi && \
 qemu-system-x86_64 -accel kvm -drive if=pflash,format=raw,readonly=on,file=/usr/share/edk2/x64/OVMF_CODE.fd -drive if=pflash,format=raw,readonly=on,file=/usr/share/edk2/x64/OVMF_VARS.fd  -drive format=raw,file=fat:rw:esp -m 1024M
+;;
+esac
@@ -0,0 +1,379 @@
+//! Multi-line text editing.
+//!
+//! The area takes the whole screen: a title line, the text, and a status line.
+//! Long lines are wrapped by display width, and the view scrolls to follow the cursor.
+
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use anyhow::{anyhow, Result};
+use unicode_width::UnicodeWidthChar;
+
+use crate::ToError;
+
+use super::console::{AcceleratorKey, Console, CursorStyle, Key, ModifierKey};
+
+/// Config of `LineEdit::edit_area`.
+pub struct EditConfig<'a> {
+    /// Shown on top of the area.
+    pub title: &'a str,
+    /// Start in replace mode instead of insert mode.
+    pub replace: bool,
+}
+
+const AREA_MIN_SIZE: (usize, usize) = (32, 5);
+
+/// Result of a key press.
+enum Action {
+    Continue,
+    Save,
+    Cancel,
+}
+
+pub(super) struct TextArea {
+    /// Text, one entry per line.
+    lines: Vec<Vec<char>>,
+    /// Cursor, as (line, column).
+    cursor: (usize, usize),
+    /// First visual row on screen.
+    top: usize,
+    is_insert: bool,
+    /// Width and height of text area.
+    width: usize,
+    height: usize,
+}
+
+impl TextArea {
+    pub(super) fn new(text: &str, is_insert: bool, width: usize, height: usize) -> Self {
+        let lines = text.split('\n').map(|x| x.chars().collect()).collect();
+        Self {
+            lines,
+            cursor: (0, 0),
+            top: 0,
+            is_insert,
+            width,
+            height,
+        }
+    }
+
+    pub(super) fn text(&self) -> String {
+        let mut ret = String::new();
+        for (i, line) in self.lines.iter().enumerate() {
+            if i != 0 {
+                ret.push('\n');
+            }
+            ret.extend(line.iter());
+        }
+        ret
+    }
+
+    /// Start of each visual row of a line, in chars.
+    ///
+    /// The cell after the last char is counted, as cursor may be there.
+    fn row_starts(&self, line: usize) -> Vec<usize> {
+        let mut ret = vec![0];
+        let mut w = 0;
+        for (i, k) in self.lines[line].iter().enumerate() {
+            let cw = char_width(*k);
+            if w + cw > self.width {
+                ret.push(i);
+                w = 0;
+            }
+            w += cw;
+        }
+        if w >= self.width {
+            ret.push(self.lines[line].len());
+        }
+        ret
+    }
+
+    /// Number of visual rows taken by a line.
+    fn rows_of(&self, line: usize) -> usize {
+        self.row_starts(line).len()
+    }
+
+    /// Visual row of the cursor in its line, and its column on screen.
+    fn cursor_position(&self) -> (usize, usize) {
+        let (line, col) = self.cursor;
+        let starts = self.row_starts(line);
+        let row = starts.iter().rposition(|x| *x <= col).unwrap_or(0);
+        let x = self.lines[line][starts[row]..col]
+            .iter()
+            .map(|k| char_width(*k))
+            .sum();
+        (row, x)
+    }
+
+    /// Visual row of the cursor, counted from the first line.
+    fn cursor_row(&self) -> usize {
+        let line = self.cursor.0;
+        (0..line).map(|x| self.rows_of(x)).sum::<usize>() + self.cursor_position().0
+    }
+
+    /// Char at screen column `x` of a visual row, or the last one of the row if it is shorter.
+    fn col_at(&self, line: usize, row: usize, x: usize) -> usize {
+        let starts = self.row_starts(line);
+        let text = &self.lines[line];
+        // Only the last row may hold cursor after its last char.
+        let end = match starts.get(row + 1) {
+            Some(next) => next - 1,
+            None => text.len(),
+        };
+        let mut col = starts[row];
+        let mut w = 0;
+        while col < end && w + char_width(text[col]) <= x {
+            w += char_width(text[col]);
+            col += 1;
+        }
+        col
+    }
+
+    fn current(&mut self) -> &mut Vec<char> {
+        &mut self.lines[self.cursor.0]
+    }
+
+    fn insert(&mut self, k: char) {
+        let (_, col) = self.cursor;
+        let is_insert = self.is_insert;
+        let line = self.current();
+        if is_insert || col == line.len() {
+            line.insert(col, k);
+        } else {
+            line[col] = k;
+        }
+        self.cursor.1 += 1;
+    }
+
+    fn newline(&mut self) {
+        let (line, col) = self.cursor;
+        let rest = self.lines[line].split_off(col);
+        self.lines.insert(line + 1, rest);
+        self.cursor = (line + 1, 0);
+    }
+
+    fn backspace(&mut self) {
+        let (line, col) = self.cursor;
+        if col > 0 {
+            self.lines[line].remove(col - 1);
+            self.cursor.1 -= 1;
+        } else if line > 0 {
+            let rest = self.lines.remove(line);
+            let prev = &mut self.lines[line - 1];
+            self.cursor = (line - 1, prev.len());
+            prev.extend(rest);
+        }
+    }
+
+    fn delete(&mut self) {
+        let (line, col) = self.cursor;
+        if col < self.lines[line].len() {
+            self.lines[line].remove(col);
+        } else if line + 1 < self.lines.len() {
+            let next = self.lines.remove(line + 1);
+            self.lines[line].extend(next);
+        }
+    }
+
+    fn left(&mut self) {
+        let (line, col) = self.cursor;
+        if col > 0 {
+            self.cursor.1 -= 1;
+        } else if line > 0 {
+            self.cursor = (line - 1, self.lines[line - 1].len());
+        }
+    }
+
+    fn right(&mut self) {
+        let (line, col) = self.cursor;
+        if col < self.lines[line].len() {
+            self.cursor.1 += 1;
+        } else if line + 1 < self.lines.len() {
+            self.cursor = (line + 1, 0);
+        }
+    }
+
+    /// Move one visual row up.
+    fn up(&mut self) {
+        let line = self.cursor.0;
+        let (row, x) = self.cursor_position();
+        if row > 0 {
+            self.cursor.1 = self.col_at(line, row - 1, x);
+        } else if line > 0 {
+            let last = self.rows_of(line - 1) - 1;
+            self.cursor = (line - 1, self.col_at(line - 1, last, x));
+        }
+    }
+
+    /// Move one visual row down.
+    fn down(&mut self) {
+        let line = self.cursor.0;
+        let (row, x) = self.cursor_position();
+        if row + 1 < self.rows_of(line) {
+            self.cursor.1 = self.col_at(line, row + 1, x);
+        } else if line + 1 < self.lines.len() {
+            self.cursor = (line + 1, self.col_at(line + 1, 0, x));
+        }
+    }
+
+    fn handle(&mut self, key: Key) -> Action {
+        match key {
+            Key::Chord(ModifierKey::Ctrl, 's') | Key::Accelerator(AcceleratorKey::F(10)) => {
+                return Action::Save
+            }
+            Key::Chord(ModifierKey::Ctrl, 'q') | Key::Accelerator(AcceleratorKey::Esc) => {
+                return Action::Cancel
+            }
+            Key::Printable(k) if !k.is_control() => self.insert(k),
+            Key::Accelerator(AcceleratorKey::Enter) => self.newline(),
+            Key::Accelerator(AcceleratorKey::Backspace) => self.backspace(),
+            Key::Accelerator(AcceleratorKey::Delete) => self.delete(),
+            Key::Accelerator(AcceleratorKey::Left) => self.left(),
+            Key::Accelerator(AcceleratorKey::Right) => self.right(),
+            Key::Accelerator(AcceleratorKey::Up) => self.up(),
+            Key::Accelerator(AcceleratorKey::Down) => self.down(),
+            Key::Accelerator(AcceleratorKey::PgUp) => (0..self.height).for_each(|_| self.up()),
+            Key::Accelerator(AcceleratorKey::PgDn) => (0..self.height).for_each(|_| self.down()),
+            Key::Accelerator(AcceleratorKey::Home) => self.cursor.1 = 0,
+            Key::Accelerator(AcceleratorKey::End) => {
+                self.cursor.1 = self.lines[self.cursor.0].len();
+            }
+            Key::Accelerator(AcceleratorKey::Insert) => self.is_insert = !self.is_insert,
+            // Spurious key input.
+            _ => {}
+        }
+        Action::Continue
+    }
+
+    /// Scroll, so the cursor is on screen.
+    fn follow_cursor(&mut self) {
+        let row = self.cursor_row();
+        if row < self.top {
+            self.top = row;
+        } else if row >= self.top + self.height {
+            self.top = row + 1 - self.height;
+        }
+    }
+
+    /// Draw text area, starting from screen row `y`.
+    fn draw(&self, console: &mut (impl Console + ?Sized), y: usize) -> Result<()> {
+        let blank = " ".repeat(self.width);
+        let mut row = 0;
+        let mut drawn = 0;
+        'outer: for (n, line) in self.lines.iter().enumerate() {
+            let starts = self.row_starts(n);
+            for (i, from) in starts.iter().enumerate() {
+                if row >= self.top {
+                    let to = starts.get(i + 1).copied().unwrap_or(line.len());
+                    console.set_cursor(0, (y + drawn) as i32)?;
+                    console.write_str(&blank).core_err()?;
+                    console.set_cursor(0, (y + drawn) as i32)?;
+                    let text: String = line[*from..to].iter().collect();
+                    console.write_str(&text).core_err()?;
+                    drawn += 1;
+                    if drawn == self.height {
+                        break 'outer;
+                    }
+                }
+                row += 1;
+            }
+        }
+        // Clear rows after the end of text.
+        for i in drawn..self.height {
+            console.set_cursor(0, (y + i) as i32)?;
+            console.write_str(&blank).core_err()?;
+        }
+        Ok(())
+    }
+
+    fn draw_cursor(&self, console: &mut (impl Console + ?Sized), y: usize) -> Result<()> {
+        let row = self.cursor_row() - self.top;
+        let x = self.cursor_position().1;
+        console.set_cursor(x as i32, (y + row) as i32)
+    }
+}
+
+/// Display width of a char. Combining chars take no column.
+fn char_width(k: char) -> usize {
+    k.width().unwrap_or(0)
+}
+
+pub(super) fn edit_area<T: ?Sized + Console>(
+    console: &mut T,
+    buf: &mut String,
+    config: &EditConfig,
+) -> Result<bool> {
+    let size = console.terminal_info()?.size;
+    if size.0 < AREA_MIN_SIZE.0 || size.1 < AREA_MIN_SIZE.1 {
+        return Err(anyhow!(
+            "console too small, expected at least {}x{}, got {}x{}.",
+            AREA_MIN_SIZE.0,
+            AREA_MIN_SIZE.1,
+            size.0,
+            size.1
+        ));
+    }
+    // Last column is left out, as writing there scrolls some consoles.
+    let mut area = TextArea::new(buf, !config.replace, size.0 - 1, size.1 - 2);
+
+    console.clear()?;
+    console.write_str(config.title).core_err()?;
+    console.set_cursor_style(&CursorStyle::Display)?;
+    let saved = loop {
+        area.follow_cursor();
+        area.draw(console, 1)?;
+        console.set_cursor(0, (size.1 - 1) as i32)?;
+        write!(
+            console,
+            "Ctrl-S/F10: save  Esc/Ctrl-Q: cancel  [{}]",
+            if area.is_insert { "INS" } else { "REP" }
+        )
+        .core_err()?;
+        area.draw_cursor(console, 1)?;
+
+        match area.handle(console.wait_for_key()?) {
+            Action::Continue => {}
+            Action::Save => break true,
+            Action::Cancel => break false,
+        }
+    };
+    console.set_cursor_style(&CursorStyle::None)?;
+    console.clear()?;
+    if saved {
+        *buf = area.text();
+    }
+    Ok(saved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap_by_width() {
+        let mut area = TextArea::new("ab中文cd\nxyz", true, 4, 3);
+        // Last row is the cell after `d`, as the row before is full.
+        assert_eq!(area.row_starts(0), [0, 3, 6]);
+        assert_eq!(area.row_starts(1), [0]);
+        // Cursor after `中` wraps to the next row, and after `文` is two columns past it.
+        area.cursor = (0, 3);
+        assert_eq!(area.cursor_position(), (1, 0));
+        area.cursor = (0, 4);
+        assert_eq!(area.cursor_position(), (1, 2));
+        area.up();
+        assert_eq!(area.cursor, (0, 2));
+        area.down();
+        area.down();
+        assert_eq!(area.cursor, (0, 6));
+        area.down();
+        assert_eq!(area.cursor, (1, 0));
+        assert_eq!(area.cursor_row(), 3);
+    }
+
+    #[test]
+    fn full_row_adds_row_for_cursor() {
+        let area = TextArea::new("abcd", true, 4, 3);
+        assert_eq!(area.row_starts(0), [0, 4]);
+        let area = TextArea::new("e\u{301}bcd", true, 4, 3);
+        assert_eq!(area.row_starts(0), [0, 5]);
+    }
+}
@@ -0,0 +1,288 @@
+//! Completion for line editing.
+//!
+//! On `Tab`, the word before cursor is completed. A word is delimited by whitespace.
+//! If more than one candidate is found, their common prefix is inserted,
+//! or the candidates are listed under the prompt when there is no common prefix to add.
+
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::vec::Vec;
+use anyhow::Result;
+use config::{BootOption, BootOptionKind};
+
+use crate::file::DirEntry;
+use crate::fs::path::is_separator;
+
+/// Source of completion candidates.
+pub trait Complete {
+    /// Candidates to replace `word` with.
+    fn complete(&mut self, word: &str) -> Vec<String>;
+}
+
+/// Kernel parameters offered by `ParameterCompleter`.
+///
+/// Parameters taking a value end with `=`.
+pub const KERNEL_PARAMETERS: &[&str] = &[
+    "acpi=",
+    "amd_iommu=",
+    "apparmor=",
+    "audit=",
+    "console=",
+    "crashkernel=",
+    "debug",
+    "earlyprintk=",
+    "efi=",
+    "fbcon=",
+    "hugepages=",
+    "ignore_loglevel",
+    "init=",
+    "initrd=",
+    "intel_iommu=",
+    "iommu=",
+    "loglevel=",
+    "lsm=",
+    "maxcpus=",
+    "mem=",
+    "mitigations=",
+    "module_blacklist=",
+    "modprobe.blacklist=",
+    "noapic",
+    "nokaslr",
+    "nomodeset",
+    "nosmp",
+    "panic=",
+    "quiet",
+    "rd.break",
+    "rd.shell",
+    "resume=",
+    "ro",
+    "root=",
+    "rootflags=",
+    "rootfstype=",
+    "rootwait",
+    "rw",
+    "selinux=",
+    "single",
+    "splash",
+    "systemd.unit=",
+    "video=",
+];
+
+/// Whether a completed word is still to be continued, so no space is added after it.
+pub(crate) fn is_partial(word: &str) -> bool {
+    word.ends_with(|c| is_separator(c) || c == '=')
+}
+
+/// Name of a candidate shown in the list, which is its last path component.
+pub(crate) fn display_name(candidate: &str) -> &str {
+    let trimmed = candidate.trim_end_matches(|c| is_separator(c) || c == '=');
+    match trimmed.rfind(|c| is_separator(c) || c == '=') {
+        Some(k) => &candidate[k + 1..],
+        None => candidate,
+    }
+}
+
+/// Longest common prefix of candidates.
+pub(crate) fn common_prefix(candidates: &[String]) -> &str {
+    let Some(first) = candidates.first() else {
+        return "";
+    };
+    let mut len = first.len();
+    for i in &candidates[1..] {
+        len = first
+            .char_indices()
+            .zip(i.chars())
+            .find(|((_, a), b)| a != b)
+            .map(|((k, _), _)| k)
+            .unwrap_or(len.min(i.len()))
+            .min(len);
+    }
+    &first[..len]
+}
+
+/// Complete file names.
+///
+/// `list_dir` lists a directory by path. File names are matched ignoring case, as FAT does.
+pub struct PathCompleter<F> {
+    list_dir: F,
+}
+
+impl<F: FnMut(&str) -> Result<Vec<DirEntry>>> PathCompleter<F> {
+    pub fn new(list_dir: F) -> Self {
+        Self { list_dir }
+    }
+}
+
+impl<F: FnMut(&str) -> Result<Vec<DirEntry>>> Complete for PathCompleter<F> {
+    fn complete(&mut self, word: &str) -> Vec<String> {
+        let (dir, prefix) = match word.rfind(is_separator) {
+            Some(k) => word.split_at(k + 1),
+            None => ("", word),
+        };
+        // Keep the separator style user has typed.
+        let separator = dir.chars().rev().find(|c| is_separator(*c)).unwrap_or('\\');
+        let Ok(entries) = (self.list_dir)(dir) else {
+            return Vec::new();
+        };
+        let prefix = prefix.to_lowercase();
+        let mut ret: Vec<String> = entries
+            .into_iter()
+            .filter(|x| x.name.to_lowercase().starts_with(&prefix))
+            .map(|x| {
+                let mut candidate = dir.to_owned();
+                candidate.push_str(&x.name);
+                if x.is_dir {
+                    candidate.push(separator);
+                }
+                candidate
+            })
+            .collect();
+        ret.sort();
+        ret
+    }
+}
+
+/// Complete kernel parameter names, from `KERNEL_PARAMETERS` and boot options.
+pub struct ParameterCompleter<'a> {
+    options: &'a [BootOption],
+}
+
+impl<'a> ParameterCompleter<'a> {
+    pub fn new(options: &'a [BootOption]) -> Self {
+        Self { options }
+    }
+
+    /// Parameters which boot options may append, with their identifiers.
+    fn option_parameters(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.options.iter().flat_map(|i| {
+            let values: Vec<(&str, &str)> = match &i.option {
+                BootOptionKind::Bool(k) | BootOptionKind::Template(k) => k
+                    .split_whitespace()
+                    .map(|x| (i.identifier.as_str(), x))
+                    .collect(),
+                BootOptionKind::Multiple(k) => k
+                    .iter()
+                    .flat_map(|x| {
+                        x.value
+                            .split_whitespace()
+                            .map(|y| (x.identifier.as_str(), y))
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+            values
+        })
+    }
+}
+
+impl<'a> Complete for ParameterCompleter<'a> {
+    fn complete(&mut self, word: &str) -> Vec<String> {
+        let upper = word.to_uppercase();
+        let mut ret: Vec<String> = KERNEL_PARAMETERS
+            .iter()
+            .copied()
+            .filter(|x| x.starts_with(word))
+            .chain(
+                // Options can be found by identifier as well.
+                self.option_parameters()
+                    .filter(|(id, x)| x.starts_with(word) || id.starts_with(upper.as_str()))
+                    .map(|(_, x)| x),
+            )
+            .map(ToOwned::to_owned)
+            .collect();
+        ret.sort();
+        ret.dedup();
+        ret
+    }
+}
+
+/// Complete a word of cmdline.
+///
+/// Values of parameters that look like paths (such as `initrd=\linux\initrd`) are completed as paths,
+/// and other words as parameter names.
+pub struct CmdlineCompleter<'a, F> {
+    parameters: ParameterCompleter<'a>,
+    paths: PathCompleter<F>,
+}
+
+impl<'a, F: FnMut(&str) -> Result<Vec<DirEntry>>> CmdlineCompleter<'a, F> {
+    pub fn new(options: &'a [BootOption], list_dir: F) -> Self {
+        Self {
+            parameters: ParameterCompleter::new(options),
+            paths: PathCompleter::new(list_dir),
+        }
+    }
+}
+
+impl<'a, F: FnMut(&str) -> Result<Vec<DirEntry>>> Complete for CmdlineCompleter<'a, F> {
+    fn complete(&mut self, word: &str) -> Vec<String> {
+        match word.split_once('=') {
+            Some((name, value)) if value.starts_with(is_separator) => {
+                let name = &word[..name.len() + 1];
+                self.paths
+                    .complete(value)
+                    .into_iter()
+                    .map(|x| {
+                        let mut ret = name.to_owned();
+                        ret.push_str(&x);
+                        ret
+                    })
+                    .collect()
+            }
+            Some(_) => Vec::new(),
+            None if word.starts_with(is_separator) => self.paths.complete(word),
+            None => self.parameters.complete(word),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn entry(name: &str, is_dir: bool) -> DirEntry {
+        DirEntry {
+            name: name.to_owned(),
+            is_dir,
+            size: 0,
+        }
+    }
+
+    fn fake_dir(path: &str) -> Result<Vec<DirEntry>> {
+        match path {
+            "" | "\\" | "/" => Ok(vec![entry("EFI", true), entry("vmlinuz", false)]),
+            "\\EFI\\" | "/EFI/" => Ok(vec![entry("Boot", true), entry("b2", true)]),
+            _ => Err(anyhow::anyhow!("not found")),
+        }
+    }
+
+    #[test]
+    fn paths_keep_separator() {
+        let mut c = PathCompleter::new(fake_dir);
+        assert_eq!(c.complete("/e"), vec!["/EFI/"]);
+        assert_eq!(c.complete("\\EFI\\b"), vec!["\\EFI\\Boot\\", "\\EFI\\b2\\"]);
+        assert!(c.complete("/nothing/").is_empty());
+    }
+
+    #[test]
+    fn cmdline_words() {
+        let mut c = CmdlineCompleter::new(&[], fake_dir);
+        assert_eq!(c.complete("initrd=\\v"), vec!["initrd=\\vmlinuz"]);
+        assert_eq!(
+            c.complete("roo"),
+            vec!["root=", "rootflags=", "rootfstype=", "rootwait"]
+        );
+        assert!(c.complete("root=/dev").is_empty());
+    }
+
+    #[test]
+    fn prefix_and_names() {
+        let candidates = vec!["rootflags=".to_owned(), "rootfstype=".to_owned()];
+        assert_eq!(common_prefix(&candidates), "rootf");
+        assert_eq!(display_name("\\EFI\\Boot\\"), "Boot\\");
+        assert_eq!(display_name("initrd=\\vmlinuz"), "vmlinuz");
+        assert_eq!(display_name("root="), "root=");
+        assert!(is_partial("root="));
+    }
+}
@@ -0,0 +1,325 @@
+use core::fmt::Write;
+
+use super::meta::ReadOne;
+use super::session::EditSession;
+use anyhow::{anyhow, Result};
+/// Key struct - represents a key pressed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Key {
+    /// Printable characters
+    Printable(char),
+    /// Non-printable key press, including arrow keys.
+    Accelerator(AcceleratorKey),
+    /// Printable key with modifier held, such as `Ctrl-A`.
+    ///
+    /// The char is in lower case. Shift is never reported here, as it is already applied to the char.
+    Chord(ModifierKey, char),
+    /// Non-printable key with modifier held, such as `Ctrl-Left`.
+    ChordAccelerator(ModifierKey, AcceleratorKey),
+    /// Unknown key press received.
+    Unknown(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AcceleratorKey {
+    Esc,
+    Tab,
+    PrtSc,
+    PgUp,
+    PgDn,
+    Up,
+    Down,
+    Left,
+    Right,
+    Backspace,
+    Home,
+    End,
+    Insert,
+    Delete,
+    Enter,
+    F(u8),
+}
+
+/// Modifier of a chord.
+///
+/// If more than one modifier is held, only the first one in this order is reported, so Shift
+/// held with another modifier does not hide it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModifierKey {
+    Ctrl,
+    Alt,
+    Shift,
+}
+
+impl Key {
+    /// Parse a key name, as used for hotkeys in config.
+    ///
+    /// A name is a single char (`w`), a key name (`Esc`, `F12`, `Delete`),
+    /// or either of them after a modifier (`Ctrl-r`, `Alt-F4`). Names are case-insensitive,
+    /// except for single chars.
+    pub fn from_name(name: &str) -> Option<Key> {
+        let mut chars = name.chars();
+        if let (Some(k), None) = (chars.next(), chars.next()) {
+            return Some(Key::Printable(k));
+        }
+        let (modifier, key) = match name.split_once('-') {
+            Some((modifier, key)) if !key.is_empty() => {
+                let modifier = match modifier.to_ascii_lowercase().as_str() {
+                    "ctrl" => ModifierKey::Ctrl,
+                    "shift" => ModifierKey::Shift,
+                    "alt" => ModifierKey::Alt,
+                    _ => return None,
+                };
+                (Some(modifier), key)
+            }
+            _ => (None, name),
+        };
+        let mut chars = key.chars();
+        let key = match (chars.next(), chars.next()) {
+            (Some(k), None) => Key::Printable(k.to_ascii_lowercase()),
+            _ => Key::Accelerator(AcceleratorKey::from_name(key)?),
+        };
+        match (modifier, key) {
+            (None, key) => Some(key),
+            (Some(modifier), Key::Printable(k)) => Some(Key::Chord(modifier, k)),
+            (Some(modifier), Key::Accelerator(k)) => Some(Key::ChordAccelerator(modifier, k)),
+            _ => None,
+        }
+    }
+}
+
+impl AcceleratorKey {
+    /// Parse a key name, such as `Esc` or `F12`.
+    pub fn from_name(name: &str) -> Option<AcceleratorKey> {
+        let name = name.to_ascii_lowercase();
+        let key = match name.as_str() {
+            "esc" | "escape" => AcceleratorKey::Esc,
+            "tab" => AcceleratorKey::Tab,
+            "prtsc" => AcceleratorKey::PrtSc,
+            "pgup" | "pageup" => AcceleratorKey::PgUp,
+            "pgdn" | "pagedown" => AcceleratorKey::PgDn,
+            "up" => AcceleratorKey::Up,
+            "down" => AcceleratorKey::Down,
+            "left" => AcceleratorKey::Left,
+            "right" => AcceleratorKey::Right,
+            "backspace" => AcceleratorKey::Backspace,
+            "home" => AcceleratorKey::Home,
+            "end" => AcceleratorKey::End,
+            "ins" | "insert" => AcceleratorKey::Insert,
+            "del" | "delete" => AcceleratorKey::Delete,
+            "enter" => AcceleratorKey::Enter,
+            k => match k.strip_prefix('f')?.parse() {
+                Ok(n @ 1..=24) => AcceleratorKey::F(n),
+                _ => return None,
+            },
+        };
+        Some(key)
+    }
+}
+
+/// What to do on startup, as requested by keys held while b2 starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartupAction {
+    /// Follow config.
+    Default,
+    /// Show menu, even if config says to boot immediately.
+    ShowMenu,
+    /// Skip config, and show fallback menu.
+    FallbackMenu,
+}
+
+impl StartupAction {
+    /// Decide action from keys pressed before startup, and modifier held on startup.
+    ///
+    /// * Holding `Shift`, or pressing `Esc` shows menu.
+    /// * Holding `Ctrl`, or pressing any `Ctrl` chord skips config.
+    pub fn from_keys(keys: &[Key], held: Option<ModifierKey>) -> Self {
+        let chord = keys.iter().find_map(|k| match k {
+            Key::Chord(m, _) | Key::ChordAccelerator(m, _) => Some(*m),
+            _ => None,
+        });
+        match held.or(chord) {
+            Some(ModifierKey::Ctrl) => Self::FallbackMenu,
+            Some(ModifierKey::Shift) => Self::ShowMenu,
+            _ if keys.contains(&Key::Accelerator(AcceleratorKey::Esc)) => Self::ShowMenu,
+            _ => Self::Default,
+        }
+    }
+}
+
+/// Style of terminal text
+#[derive(Debug, Clone)]
+pub struct ConsoleStyle {
+    /// Bold text.
+    pub bold: bool,
+    /// Highlighted text.
+    pub reverse: bool,
+    /// Blinking text.
+    pub blink: bool,
+}
+
+impl ConsoleStyle {
+    pub const BOLD: Self = Self {
+        bold: true,
+        reverse: false,
+        blink: false,
+    };
+    pub const REV: Self = Self {
+        bold: false,
+        reverse: true,
+        blink: false,
+    };
+    pub const BOLDREV: Self = Self {
+        bold: true,
+        reverse: true,
+        blink: false,
+    };
+}
+
+/// Terminal information.
+#[derive(Debug, Clone)]
+pub struct TerminalInfo {
+    /// Terminal Size.
+    pub size: (usize, usize),
+}
+
+#[derive(Debug, Clone)]
+pub enum CursorStyle {
+    /// Hide cursor.
+    None,
+    /// Display cursor.
+    Display,
+}
+
+/// Text console
+///
+/// Simple console with cursor control.
+pub trait Console: Write + ReadOne<Key> {
+    /// Wait until a key pressed.
+    fn wait_for_key(&mut self) -> Result<Key> {
+        self.read_one()
+    }
+
+    /// Read a key input immediately.
+    fn read_key(&mut self) -> Result<Option<Key>> {
+        self.read_one().map(Some)
+    }
+
+    /// Get current cursor position in terminal.
+    ///
+    /// The upper left corner of the screen is defined as coord (0, 0) .
+    fn get_cursor(&mut self) -> Result<(i32, i32)>;
+
+    /// Set cursor position in terminal.
+    ///
+    /// An error will be generated if cursor position is invalid.
+    fn set_cursor(&mut self, x: i32, y: i32) -> Result<()>;
+
+    /// Get terminal information.
+    fn terminal_info(&mut self) -> Result<TerminalInfo> {
+        todo!()
+    }
+
+    /// Set cursor style.
+    ///
+    /// **NOTE**: This method may only return error if underlying console reports problem.
+    /// If given cursor style does not exist, console driver should set cursor style to the most appropriate one or simply do nothing.
+    fn set_cursor_style(&mut self, _style: &CursorStyle) -> Result<()> {
+        Ok(())
+    }
+
+    /// Line editing state of this console, such as history.
+    ///
+    /// Consoles without one edit each line from scratch.
+    fn edit_session(&mut self) -> Option<&mut EditSession> {
+        None
+    }
+
+    /// Try resizing terminal, and return dimension if succeed.
+    fn resize(&mut self) -> Result<(i32, i32)> {
+        Err(anyhow!("this terminal does not support auto resizing"))
+    }
+
+    /// Write text at current curson with given style.
+    fn write_with_style(&mut self, text: &str, _style: ConsoleStyle) -> Result<()> {
+        self.write_str(text)
+            .map_err(|_| anyhow!("failed to write!"))
+    }
+
+    /// Clear screen, and move cursor to the upper left corner.
+    fn clear(&mut self) -> Result<()> {
+        let size = self.terminal_info()?.size;
+        let blank = " ".repeat(size.0.saturating_sub(1));
+        for y in 0..size.1 {
+            self.set_cursor(0, y as i32)?;
+            self.write_str(&blank)
+                .map_err(|_| anyhow!("failed to write!"))?;
+        }
+        self.set_cursor(0, 0)
+    }
+
+    /// Reset console to a known clean state.
+    ///
+    /// This includes clearing screen, reset cursor, text style and key binding if possible.
+    fn reset(&mut self) -> Result<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_names() {
+        assert_eq!(Key::from_name("w"), Some(Key::Printable('w')));
+        assert_eq!(Key::from_name("W"), Some(Key::Printable('W')));
+        assert_eq!(Key::from_name("-"), Some(Key::Printable('-')));
+        assert_eq!(
+            Key::from_name("F12"),
+            Some(Key::Accelerator(AcceleratorKey::F(12)))
+        );
+        assert_eq!(
+            Key::from_name("ctrl-R"),
+            Some(Key::Chord(ModifierKey::Ctrl, 'r'))
+        );
+        assert_eq!(
+            Key::from_name("Alt-F4"),
+            Some(Key::ChordAccelerator(
+                ModifierKey::Alt,
+                AcceleratorKey::F(4)
+            ))
+        );
+        assert_eq!(Key::from_name("F0"), None);
+        assert_eq!(Key::from_name("Hyper-x"), None);
+    }
+
+    #[test]
+    fn startup_action() {
+        let esc = Key::Accelerator(AcceleratorKey::Esc);
+        let ctrl_c = Key::Chord(ModifierKey::Ctrl, 'c');
+        assert_eq!(StartupAction::from_keys(&[], None), StartupAction::Default);
+        assert_eq!(
+            StartupAction::from_keys(&[Key::Printable('a')], None),
+            StartupAction::Default
+        );
+        assert_eq!(
+            StartupAction::from_keys(core::slice::from_ref(&esc), None),
+            StartupAction::ShowMenu
+        );
+        assert_eq!(
+            StartupAction::from_keys(&[], Some(ModifierKey::Shift)),
+            StartupAction::ShowMenu
+        );
+        assert_eq!(
+            StartupAction::from_keys(&[esc, ctrl_c], None),
+            StartupAction::FallbackMenu
+        );
+        assert_eq!(
+            StartupAction::from_keys(&[], Some(ModifierKey::Ctrl)),
+            StartupAction::FallbackMenu
+        );
+        assert_eq!(
+            StartupAction::from_keys(&[], Some(ModifierKey::Alt)),
+            StartupAction::Default
+        );
+    }
+}
@@ -0,0 +1,10 @@
+use alloc::string::String;
+
+/// An entry of a directory.
+#[derive(Debug, Clone)]
+pub struct DirEntry {
+    pub name: String,
+    pub is_dir: bool,
+    /// File size in bytes, 0 for directories.
+    pub size: u64,
+}
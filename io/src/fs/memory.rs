@@ -0,0 +1,216 @@
+//! In-memory file system, to test code built on `FileSystem` and `Volumes` on host.
+
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use anyhow::{anyhow, Result};
+
+use super::path::normalize;
+use super::{Directory, FileSystem, Metadata, RegularFile, VolumeInfo, Volumes};
+use crate::file::DirEntry;
+
+/// Files by normalized path. Directories are made by files in them.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryFs {
+    pub label: String,
+    files: BTreeMap<String, Vec<u8>>,
+}
+
+impl MemoryFs {
+    pub fn new(label: &str) -> Self {
+        Self {
+            label: label.to_owned(),
+            files: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_file(mut self, path: &str, data: &[u8]) -> Self {
+        self.files.insert(normalize(path), data.to_owned());
+        self
+    }
+
+    fn is_dir(&self, path: &str) -> bool {
+        let prefix = dir_prefix(path);
+        path == "\\" || self.files.keys().any(|x| x.starts_with(&prefix))
+    }
+}
+
+/// Prefix of paths in a normalized directory.
+fn dir_prefix(path: &str) -> String {
+    let mut ret = path.trim_end_matches('\\').to_owned();
+    ret.push('\\');
+    ret
+}
+
+struct MemoryFile {
+    data: Vec<u8>,
+    pos: usize,
+}
+
+impl RegularFile for MemoryFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let rest = self.data.get(self.pos..).unwrap_or_default();
+        let n = rest.len().min(buf.len());
+        buf[..n].copy_from_slice(&rest[..n]);
+        self.pos += n;
+        Ok(n)
+    }
+
+    fn seek(&mut self, pos: u64) -> Result<()> {
+        self.pos = pos as usize;
+        Ok(())
+    }
+
+    fn size(&self) -> u64 {
+        self.data.len() as u64
+    }
+}
+
+struct MemoryDir(Vec<DirEntry>);
+
+impl Directory for MemoryDir {
+    fn entries(&mut self) -> Result<Vec<DirEntry>> {
+        Ok(self.0.clone())
+    }
+}
+
+impl FileSystem for MemoryFs {
+    fn open(&mut self, path: &str) -> Result<Box<dyn RegularFile>> {
+        let data = self
+            .files
+            .get(&normalize(path))
+            .ok_or_else(|| anyhow!("{} not found.", path))?;
+        Ok(Box::new(MemoryFile {
+            data: data.clone(),
+            pos: 0,
+        }))
+    }
+
+    fn open_dir(&mut self, path: &str) -> Result<Box<dyn Directory>> {
+        let path = normalize(path);
+        if !self.is_dir(&path) {
+            return Err(anyhow!("{} is not a directory.", path));
+        }
+        let prefix = dir_prefix(&path);
+        let mut entries: Vec<DirEntry> = Vec::new();
+        for (file, data) in self.files.iter() {
+            let Some(rest) = file.strip_prefix(&prefix) else {
+                continue;
+            };
+            let mut rest = rest.split('\\');
+            let (Some(name), next) = (rest.next(), rest.next()) else {
+                continue;
+            };
+            if name.is_empty() || entries.iter().any(|x| x.name == name) {
+                continue;
+            }
+            entries.push(DirEntry {
+                name: name.to_owned(),
+                is_dir: next.is_some(),
+                size: if next.is_some() { 0 } else { data.len() as u64 },
+            });
+        }
+        Ok(Box::new(MemoryDir(entries)))
+    }
+
+    fn stat(&mut self, path: &str) -> Result<Metadata> {
+        let path = normalize(path);
+        match self.files.get(&path) {
+            Some(data) => Ok(Metadata {
+                is_dir: false,
+                size: data.len() as u64,
+            }),
+            None if self.is_dir(&path) => Ok(Metadata {
+                is_dir: true,
+                size: 0,
+            }),
+            None => Err(anyhow!("{} not found.", path)),
+        }
+    }
+
+    fn label(&mut self) -> Result<String> {
+        Ok(self.label.clone())
+    }
+}
+
+/// Volumes made of in-memory file systems.
+#[derive(Default)]
+pub struct MemoryVolumes(pub Vec<(VolumeInfo, MemoryFs)>);
+
+impl Volumes for MemoryVolumes {
+    fn volumes(&mut self) -> Result<Vec<VolumeInfo>> {
+        Ok(self.0.iter().map(|x| x.0.clone()).collect())
+    }
+
+    fn mount(&mut self, index: usize) -> Result<Box<dyn FileSystem>> {
+        let (_, fs) = self
+            .0
+            .get(index)
+            .ok_or_else(|| anyhow!("no volume {}.", index))?;
+        Ok(Box::new(fs.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::VolumeAddress;
+
+    fn esp() -> MemoryFs {
+        MemoryFs::new("ESP")
+            .with_file("/EFI/b2/b2.efi", b"MZ")
+            .with_file("/EFI/b2/b2.conf", b"{}")
+            .with_file("/vmlinuz", b"0123456789")
+    }
+
+    #[test]
+    fn list_and_stat() {
+        let mut fs = esp();
+        let root = fs.read_dir("/").unwrap();
+        let names: Vec<(&str, bool)> = root.iter().map(|x| (x.name.as_str(), x.is_dir)).collect();
+        assert_eq!(names, [("EFI", true), ("vmlinuz", false)]);
+        assert_eq!(fs.read_dir("\\EFI\\b2\\").unwrap().len(), 2);
+        assert!(fs.stat("EFI/b2").unwrap().is_dir);
+        assert_eq!(fs.stat("/vmlinuz").unwrap().size, 10);
+        assert!(fs.stat("/missing").is_err());
+        assert!(fs.read_dir("/vmlinuz").is_err());
+    }
+
+    #[test]
+    fn partial_reads() {
+        let mut fs = esp();
+        let mut file = fs.open("\\vmlinuz").unwrap();
+        let mut buf = [0u8; 4];
+        assert_eq!(file.read_at(8, &mut buf).unwrap(), 2);
+        assert_eq!(&buf[..2], b"89");
+        file.seek(2).unwrap();
+        assert_eq!(file.read(&mut buf).unwrap(), 4);
+        assert_eq!(&buf, b"2345");
+        assert_eq!(file.read_all().unwrap(), b"0123456789");
+    }
+
+    #[test]
+    fn mount_by_address() {
+        let info = |index, label: &str| VolumeInfo {
+            index,
+            label: label.to_owned(),
+            partition_guid: None,
+            device_path: String::new(),
+            current: index == 0,
+        };
+        let mut volumes = MemoryVolumes(alloc::vec![
+            (info(0, "ESP"), esp()),
+            (
+                info(1, "boot"),
+                MemoryFs::new("boot").with_file("/initrd.img", b"rd")
+            ),
+        ]);
+        let address = VolumeAddress::parse("LABEL=boot").unwrap();
+        let mut fs = volumes.mount_by(&address).unwrap();
+        assert_eq!(fs.read("/initrd.img").unwrap(), b"rd");
+        let mut fs = volumes.mount_by(&VolumeAddress::Current).unwrap();
+        assert_eq!(fs.label().unwrap(), "ESP");
+    }
+}
@@ -0,0 +1,253 @@
+//! File systems and volumes.
+//!
+//! A platform lists its volumes with `Volumes`, and each mounted volume is a `FileSystem`.
+//! Paths take either `/` or `\` as separator, and are normalized by `path::normalize`
+//! before reaching a backend.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use anyhow::{anyhow, Result};
+
+use crate::file::DirEntry;
+
+pub mod memory;
+pub mod path;
+
+/// Metadata of a file or directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Metadata {
+    pub is_dir: bool,
+    /// File size in bytes, 0 for directories.
+    pub size: u64,
+}
+
+/// An opened regular file, with a position to read from.
+pub trait RegularFile {
+    /// Read from current position, and advance it. Returns bytes read, which is 0 at end of file.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+    /// Set current position. Position after end of file reads nothing.
+    fn seek(&mut self, pos: u64) -> Result<()>;
+
+    /// File size in bytes.
+    fn size(&self) -> u64;
+
+    /// Read `buf.len()` bytes at `pos`, or less at end of file.
+    fn read_at(&mut self, pos: u64, buf: &mut [u8]) -> Result<usize> {
+        self.seek(pos)?;
+        let mut n = 0;
+        while n < buf.len() {
+            match self.read(&mut buf[n..])? {
+                0 => break,
+                k => n += k,
+            }
+        }
+        Ok(n)
+    }
+
+    /// Read whole file.
+    fn read_all(&mut self) -> Result<Vec<u8>> {
+        let size = usize::try_from(self.size()).map_err(|_| anyhow!("file too large."))?;
+        let mut buf = Vec::new();
+        buf.try_reserve(size)
+            .map_err(|_| anyhow!("file too large to fit in memory."))?;
+        buf.resize(size, 0);
+        let n = self.read_at(0, &mut buf)?;
+        buf.truncate(n);
+        Ok(buf)
+    }
+}
+
+/// An opened directory.
+pub trait Directory {
+    /// List entries, except `.` and `..`.
+    fn entries(&mut self) -> Result<Vec<DirEntry>>;
+}
+
+/// A mounted file system.
+///
+/// Paths are absolute, and normalized with `path::normalize` first.
+pub trait FileSystem {
+    fn open(&mut self, path: &str) -> Result<Box<dyn RegularFile>>;
+
+    fn open_dir(&mut self, path: &str) -> Result<Box<dyn Directory>>;
+
+    fn stat(&mut self, path: &str) -> Result<Metadata>;
+
+    /// Label of file system, which may be empty.
+    fn label(&mut self) -> Result<String>;
+
+    /// List entries of a directory.
+    fn read_dir(&mut self, path: &str) -> Result<Vec<DirEntry>> {
+        self.open_dir(path)?.entries()
+    }
+
+    /// Read a whole file.
+    fn read(&mut self, path: &str) -> Result<Vec<u8>> {
+        self.open(path)?.read_all()
+    }
+}
+
+/// A volume with a file system, as listed by `Volumes`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumeInfo {
+    /// Index in the list of volumes.
+    pub index: usize,
+    /// File system label, which may be empty.
+    pub label: String,
+    /// GUID of the GPT partition holding the volume, in lower case.
+    pub partition_guid: Option<String>,
+    /// Platform specific path of the volume, such as EFI device path text.
+    pub device_path: String,
+    /// Whether b2 is loaded from this volume.
+    pub current: bool,
+}
+
+/// How a volume is addressed by user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VolumeAddress {
+    /// Volume b2 is loaded from.
+    Current,
+    /// Index in the list of volumes, as `#<n>`.
+    Index(usize),
+    /// File system label, as `LABEL=<label>`.
+    Label(String),
+    /// GPT partition GUID, as `PARTUUID=<guid>`.
+    PartUuid(String),
+    /// Full device path text.
+    DevicePath(String),
+}
+
+impl VolumeAddress {
+    /// Parse an address. Anything unrecognized is taken as device path text.
+    pub fn parse(s: &str) -> Result<Self> {
+        if s.is_empty() {
+            return Ok(Self::Current);
+        }
+        if let Some(n) = s.strip_prefix('#') {
+            return n
+                .parse()
+                .map(Self::Index)
+                .map_err(|_| anyhow!("invalid volume index {}.", n));
+        }
+        match s.split_once('=') {
+            Some((key, value)) if key.eq_ignore_ascii_case("LABEL") => {
+                Ok(Self::Label(value.into()))
+            }
+            Some((key, value)) if key.eq_ignore_ascii_case("PARTUUID") => {
+                Ok(Self::PartUuid(value.to_ascii_lowercase()))
+            }
+            _ => Ok(Self::DevicePath(s.into())),
+        }
+    }
+
+    pub fn matches(&self, volume: &VolumeInfo) -> bool {
+        match self {
+            Self::Current => volume.current,
+            Self::Index(n) => volume.index == *n,
+            Self::Label(label) => volume.label.eq_ignore_ascii_case(label),
+            Self::PartUuid(guid) => volume.partition_guid.as_deref() == Some(guid.as_str()),
+            Self::DevicePath(path) => volume.device_path.eq_ignore_ascii_case(path),
+        }
+    }
+}
+
+/// A path on a volume, as `<volume>:<path>` such as `LABEL=boot:/vmlinuz`,
+/// or a plain path on the volume b2 is loaded from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumePath {
+    pub volume: VolumeAddress,
+    /// Normalized path on the volume.
+    pub path: String,
+}
+
+impl VolumePath {
+    pub fn parse(s: &str) -> Result<Self> {
+        // Device path text may contain `:` as well, so volume ends at the last `:` before a separator.
+        let split = s
+            .match_indices(':')
+            .map(|(k, _)| k)
+            .rfind(|&k| s[k + 1..].starts_with(path::is_separator));
+        Ok(match split {
+            Some(k) => Self {
+                volume: VolumeAddress::parse(&s[..k])?,
+                path: path::normalize(&s[k + 1..]),
+            },
+            None => Self {
+                volume: VolumeAddress::Current,
+                path: path::normalize(s),
+            },
+        })
+    }
+}
+
+/// Volumes of a platform.
+pub trait Volumes {
+    fn volumes(&mut self) -> Result<Vec<VolumeInfo>>;
+
+    /// Mount a volume by index.
+    fn mount(&mut self, index: usize) -> Result<Box<dyn FileSystem>>;
+
+    /// Find a volume by address.
+    fn find(&mut self, address: &VolumeAddress) -> Result<VolumeInfo> {
+        self.volumes()?
+            .into_iter()
+            .find(|x| address.matches(x))
+            .ok_or_else(|| anyhow!("no volume matches {:?}.", address))
+    }
+
+    /// Mount a volume by address.
+    fn mount_by(&mut self, address: &VolumeAddress) -> Result<Box<dyn FileSystem>> {
+        let index = self.find(address)?.index;
+        self.mount(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::borrow::ToOwned;
+
+    fn volume(index: usize, label: &str, guid: Option<&str>) -> VolumeInfo {
+        VolumeInfo {
+            index,
+            label: label.to_owned(),
+            partition_guid: guid.map(ToOwned::to_owned),
+            device_path: alloc::format!("PciRoot(0x0)/HD({})", index),
+            current: index == 0,
+        }
+    }
+
+    #[test]
+    fn volume_addresses() {
+        let esp = volume(0, "ESP", Some("0fc63daf-8483-4772-8e79-3d69d8477de4"));
+        let boot = volume(1, "boot", None);
+        assert!(VolumeAddress::parse("").unwrap().matches(&esp));
+        assert!(VolumeAddress::parse("#1").unwrap().matches(&boot));
+        assert!(VolumeAddress::parse("LABEL=BOOT").unwrap().matches(&boot));
+        assert!(!VolumeAddress::parse("LABEL=BOOT").unwrap().matches(&esp));
+        let partuuid = VolumeAddress::parse("PARTUUID=0FC63DAF-8483-4772-8E79-3D69D8477DE4");
+        assert!(partuuid.unwrap().matches(&esp));
+        let path = VolumeAddress::parse("PciRoot(0x0)/HD(1)").unwrap();
+        assert!(path.matches(&boot));
+        assert!(VolumeAddress::parse("#x").is_err());
+    }
+
+    #[test]
+    fn volume_paths() {
+        let path = VolumePath::parse("LABEL=boot:/vmlinuz").unwrap();
+        assert_eq!(path.volume, VolumeAddress::Label("boot".to_owned()));
+        assert_eq!(path.path, "\\vmlinuz");
+        let path = VolumePath::parse("/EFI/b2/b2.efi").unwrap();
+        assert_eq!(path.volume, VolumeAddress::Current);
+        assert_eq!(path.path, "\\EFI\\b2\\b2.efi");
+        let text =
+            "PciRoot(0x0)/Pci(0x1,0x1)/Ata(Primary,Master,0x0)/HD(1,MBR,0x1234,0x800,0x1000)";
+        let path = VolumePath::parse(&alloc::format!("{}:\\initrd.img", text)).unwrap();
+        assert_eq!(path.volume, VolumeAddress::DevicePath(text.to_owned()));
+        assert_eq!(path.path, "\\initrd.img");
+        let path = VolumePath::parse("PARTUUID=ABC:\\linux\\vmlinuz").unwrap();
+        assert_eq!(path.volume, VolumeAddress::PartUuid("abc".to_owned()));
+    }
+}
@@ -0,0 +1,73 @@
+//! Paths of file systems.
+//!
+//! Config and users may write paths with `/` or `\`, while firmware only takes `\`.
+//! A normalized path is absolute, separated by `\`, and has no `.`, `..` or trailing separator.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+pub const SEPARATOR: char = '\\';
+
+pub fn is_separator(c: char) -> bool {
+    c == '/' || c == '\\'
+}
+
+/// Normalize a path. Relative paths are taken from root.
+///
+/// `..` at root stays at root.
+pub fn normalize(path: &str) -> String {
+    let mut components: Vec<&str> = Vec::new();
+    for i in path.split(is_separator) {
+        match i {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            k => components.push(k),
+        }
+    }
+    let mut ret = String::with_capacity(path.len() + 1);
+    for i in components {
+        ret.push(SEPARATOR);
+        ret.push_str(i);
+    }
+    if ret.is_empty() {
+        ret.push(SEPARATOR);
+    }
+    ret
+}
+
+/// Join `name` to directory `dir`, and normalize.
+pub fn join(dir: &str, name: &str) -> String {
+    let mut ret = String::from(dir);
+    ret.push(SEPARATOR);
+    ret.push_str(name);
+    normalize(&ret)
+}
+
+/// Parent of a path, which is root for root.
+pub fn parent(path: &str) -> String {
+    join(path, "..")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_separators() {
+        assert_eq!(normalize("/linux/vmlinuz"), "\\linux\\vmlinuz");
+        assert_eq!(normalize("\\linux\\initrd.gz"), "\\linux\\initrd.gz");
+        assert_eq!(normalize("EFI//b2/./b2.efi/"), "\\EFI\\b2\\b2.efi");
+        assert_eq!(normalize("/EFI/../../boot"), "\\boot");
+        assert_eq!(normalize(""), "\\");
+        assert_eq!(normalize("/"), "\\");
+    }
+
+    #[test]
+    fn components() {
+        assert_eq!(join("\\EFI", "b2/drivers"), "\\EFI\\b2\\drivers");
+        assert_eq!(parent("\\EFI\\b2"), "\\EFI");
+        assert_eq!(parent("\\"), "\\");
+    }
+}
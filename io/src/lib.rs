@@ -0,0 +1,37 @@
+//! Console, line editing and file system layer of b2.
+//!
+//! Nothing here touches firmware, so it is built and tested on host.
+
+#![no_std]
+
+extern crate alloc;
+
+mod area;
+pub mod complete;
+pub mod console;
+pub mod file;
+pub mod fs;
+mod line;
+mod meta;
+#[cfg(test)]
+mod mock;
+mod session;
+pub use area::EditConfig;
+pub use meta::{
+    BlockDevice, Handle, LineEdit, Read, ReadOne, ReadSecret, ReadString, Stream, Write,
+    WriteString,
+};
+pub use session::EditSession;
+
+use anyhow::{anyhow, Result};
+
+/// Convert errors of formatting, which carry no reason.
+pub(crate) trait ToError<U> {
+    fn core_err(self) -> Result<U>;
+}
+
+impl ToError<()> for core::fmt::Result {
+    fn core_err(self) -> Result<()> {
+        self.map_err(|_| anyhow!("failed to format."))
+    }
+}
@@ -0,0 +1,753 @@
+//! Single line editing.
+//!
+//! Text is handled in grapheme clusters, and measured in display columns,
+//! so combining marks and East Asian wide characters are kept intact on screen.
+//! Cursor and viewport are byte offsets into the buffer, always on a grapheme boundary.
+//!
+//! Keys follow Emacs where the console reports modifiers:
+//!
+//! * `Ctrl-A`/`Ctrl-E`: start/end of line, `Ctrl-B`/`Ctrl-F`: back/forward one char.
+//! * `Alt-B`/`Alt-F`, `Ctrl-Left`/`Ctrl-Right`: back/forward one word.
+//! * `Ctrl-W`: kill previous space-separated word, `Alt-Backspace`: kill previous word,
+//!   `Alt-D`: kill next word, `Ctrl-K`: kill to end, `Ctrl-U`: kill to start.
+//! * `Ctrl-Y`: yank last killed text, `Alt-Y`: replace the yanked text with older one.
+//! * `Up`/`Ctrl-P`, `Down`/`Ctrl-N`: browse history of the prompt.
+//! * `Tab`: complete the word before cursor, if a completer is given.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use anyhow::{anyhow, Context, Result};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::ToError;
+
+use super::complete::{self, Complete};
+use super::console::{AcceleratorKey, Console, CursorStyle, Key, ModifierKey};
+use super::session::EditSession;
+
+const LINEEDIT_MIN_LENGTH: usize = 32;
+
+/// Display width of text, in columns.
+pub(super) fn width(s: &str) -> usize {
+    s.graphemes(true).map(|g| g.width()).sum()
+}
+
+/// Offset of the grapheme boundary after `pos`.
+fn next_boundary(s: &str, pos: usize) -> Option<usize> {
+    s[pos..].graphemes(true).next().map(|g| pos + g.len())
+}
+
+/// Offset of the grapheme boundary before `pos`.
+fn prev_boundary(s: &str, pos: usize) -> Option<usize> {
+    s[..pos].graphemes(true).next_back().map(|g| pos - g.len())
+}
+
+fn is_word(g: &str) -> bool {
+    g.chars().next().is_some_and(|c| c.is_alphanumeric())
+}
+
+fn is_space(g: &str) -> bool {
+    g.chars().all(|c| c.is_whitespace())
+}
+
+/// Offset after skipping graphemes matching `skip`, then those not matching.
+fn scan_forward(s: &str, pos: usize, skip: fn(&str) -> bool) -> usize {
+    let mut graphemes = s[pos..].graphemes(true).peekable();
+    let mut end = pos;
+    while let Some(g) = graphemes.next_if(|g| skip(g)) {
+        end += g.len();
+    }
+    while let Some(g) = graphemes.next_if(|g| !skip(g)) {
+        end += g.len();
+    }
+    end
+}
+
+/// Like `scan_forward`, but backwards.
+fn scan_backward(s: &str, pos: usize, skip: fn(&str) -> bool) -> usize {
+    let mut graphemes = s[..pos].graphemes(true).rev().peekable();
+    let mut start = pos;
+    while let Some(g) = graphemes.next_if(|g| skip(g)) {
+        start -= g.len();
+    }
+    while let Some(g) = graphemes.next_if(|g| !skip(g)) {
+        start -= g.len();
+    }
+    start
+}
+
+/// What the last key did, as some keys act differently after a kill or yank.
+#[derive(Clone, Copy, PartialEq)]
+enum LastAction {
+    Other,
+    Kill,
+    /// Yanked text in `start..end`, which is the `n`th in kill ring.
+    Yank {
+        start: usize,
+        end: usize,
+        n: usize,
+    },
+}
+
+struct LineState<'a> {
+    /// Cursor position.
+    pos: usize,
+    /// First visible position.
+    left: usize,
+    is_insert: bool,
+    /// Columns available for text.
+    available_width: usize,
+    /// Shown in place of each grapheme, for secrets.
+    mask: Option<char>,
+    prompt: &'a str,
+    session: EditSession,
+    /// Entry shown while browsing history.
+    history_pos: Option<usize>,
+    /// Line being edited before browsing history.
+    draft: String,
+    last: LastAction,
+}
+
+impl<'a> LineState<'a> {
+    /// Display width of text, taking mask into account.
+    fn width(&self, s: &str) -> usize {
+        match self.mask {
+            Some(_) => s.graphemes(true).count(),
+            None => width(s),
+        }
+    }
+
+    /// Move viewport, so the cursor stays in the visible range.
+    fn follow_cursor(&mut self, buf: &str) {
+        if self.pos < self.left {
+            self.left = self.pos;
+        }
+        while self.width(&buf[self.left..self.pos]) >= self.available_width {
+            match next_boundary(buf, self.left) {
+                Some(k) => self.left = k,
+                None => break,
+            }
+        }
+    }
+
+    /// Visible part of text, as shown on screen.
+    fn visible(&self, buf: &str) -> String {
+        let mut ret = String::new();
+        let mut w = 0;
+        for g in buf[self.left..].graphemes(true) {
+            w += self.width(g);
+            if w > self.available_width {
+                break;
+            }
+            match self.mask {
+                Some(mask) => ret.push(mask),
+                None => ret.push_str(g),
+            }
+        }
+        ret
+    }
+
+    /// Move cursor by at most `cols` columns.
+    fn move_cols(&mut self, buf: &str, cols: usize, forward: bool) {
+        let mut moved = 0;
+        while moved < cols {
+            let next = if forward {
+                next_boundary(buf, self.pos)
+            } else {
+                prev_boundary(buf, self.pos)
+            };
+            let Some(next) = next else {
+                break;
+            };
+            moved += self
+                .width(&buf[next.min(self.pos)..next.max(self.pos)])
+                .max(1);
+            self.pos = next;
+        }
+    }
+
+    /// Remove `from..to`, and put it in kill ring.
+    ///
+    /// Consecutive kills are joined into one entry.
+    fn kill(&mut self, buf: &mut String, from: usize, to: usize) {
+        // Secrets never go to kill ring.
+        if self.mask.is_none() {
+            let forward = from == self.pos;
+            let append = self.last == LastAction::Kill;
+            self.session.kill(&buf[from..to], append, forward);
+        }
+        buf.replace_range(from..to, "");
+        self.pos = from;
+    }
+
+    /// Insert `n`th killed text at cursor.
+    fn yank(&mut self, buf: &mut String, n: usize) -> LastAction {
+        let Some(text) = self.session.killed(n) else {
+            return LastAction::Other;
+        };
+        let start = self.pos;
+        buf.insert_str(start, text);
+        self.pos += text.len();
+        LastAction::Yank {
+            start,
+            end: self.pos,
+            n,
+        }
+    }
+
+    /// Show an entry of history, `None` being the draft.
+    fn show_history(&mut self, buf: &mut String, n: Option<usize>) {
+        if self.history_pos.is_none() {
+            self.draft = buf.clone();
+        }
+        let line = match n {
+            Some(n) => &self.session.history(self.prompt)[n],
+            None => &self.draft,
+        };
+        buf.clone_from(line);
+        self.pos = buf.len();
+        self.history_pos = n;
+    }
+
+    /// Complete the word before cursor.
+    ///
+    /// Returns candidates to be listed, if the word is ambiguous and can not be extended.
+    fn complete(&mut self, buf: &mut String, completer: &mut dyn Complete) -> Vec<String> {
+        let start = buf[..self.pos]
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map(|(k, c)| k + c.len_utf8())
+            .unwrap_or(0);
+        let word = &buf[start..self.pos];
+        let mut candidates = completer.complete(word);
+        let replace = match candidates.as_mut_slice() {
+            [] => return candidates,
+            [only] => {
+                if !complete::is_partial(only) {
+                    only.push(' ');
+                }
+                only.as_str()
+            }
+            _ => {
+                let prefix = complete::common_prefix(&candidates);
+                if prefix.len() <= word.len() {
+                    return candidates;
+                }
+                prefix
+            }
+        };
+        buf.replace_range(start..self.pos, replace);
+        self.pos = start + replace.len();
+        Vec::new()
+    }
+
+    /// Handle a key. Returns `true` if line is finished.
+    fn handle(&mut self, buf: &mut String, key: Key) -> bool {
+        use AcceleratorKey as A;
+        use ModifierKey::{Alt, Ctrl};
+
+        let mut last = LastAction::Other;
+        match key {
+            Key::Printable(k) if !k.is_control() => {
+                if !self.is_insert {
+                    if let Some(next) = next_boundary(buf, self.pos) {
+                        buf.replace_range(self.pos..next, "");
+                    }
+                }
+                buf.insert(self.pos, k);
+                self.pos += k.len_utf8();
+            }
+            Key::Accelerator(A::Enter) => return true,
+            Key::Accelerator(A::Backspace) => {
+                if let Some(prev) = prev_boundary(buf, self.pos) {
+                    buf.replace_range(prev..self.pos, "");
+                    self.pos = prev;
+                }
+            }
+            Key::Accelerator(A::Delete) | Key::Chord(Ctrl, 'd') => {
+                if let Some(next) = next_boundary(buf, self.pos) {
+                    buf.replace_range(self.pos..next, "");
+                }
+            }
+            Key::Accelerator(A::Left) | Key::Chord(Ctrl, 'b') => {
+                self.pos = prev_boundary(buf, self.pos).unwrap_or(self.pos)
+            }
+            Key::Accelerator(A::Right) | Key::Chord(Ctrl, 'f') => {
+                self.pos = next_boundary(buf, self.pos).unwrap_or(self.pos)
+            }
+            Key::Accelerator(A::Home) | Key::Chord(Ctrl, 'a') => self.pos = 0,
+            Key::Accelerator(A::End) | Key::Chord(Ctrl, 'e') => self.pos = buf.len(),
+            Key::ChordAccelerator(Ctrl, A::Left) | Key::Chord(Alt, 'b') => {
+                self.pos = scan_backward(buf, self.pos, |g| !is_word(g))
+            }
+            Key::ChordAccelerator(Ctrl, A::Right) | Key::Chord(Alt, 'f') => {
+                self.pos = scan_forward(buf, self.pos, |g| !is_word(g))
+            }
+            // Kill.
+            Key::Chord(Ctrl, 'w') => {
+                let from = scan_backward(buf, self.pos, is_space);
+                self.kill(buf, from, self.pos);
+                last = LastAction::Kill;
+            }
+            Key::ChordAccelerator(Ctrl | Alt, A::Backspace) => {
+                let from = scan_backward(buf, self.pos, |g| !is_word(g));
+                self.kill(buf, from, self.pos);
+                last = LastAction::Kill;
+            }
+            Key::Chord(Alt, 'd') => {
+                let to = scan_forward(buf, self.pos, |g| !is_word(g));
+                self.kill(buf, self.pos, to);
+                last = LastAction::Kill;
+            }
+            Key::Chord(Ctrl, 'k') => {
+                self.kill(buf, self.pos, buf.len());
+                last = LastAction::Kill;
+            }
+            Key::Chord(Ctrl, 'u') => {
+                self.kill(buf, 0, self.pos);
+                last = LastAction::Kill;
+            }
+            // Yank.
+            Key::Chord(Ctrl, 'y') => last = self.yank(buf, 0),
+            Key::Chord(Alt, 'y') => {
+                if let LastAction::Yank { start, end, n } = self.last {
+                    buf.replace_range(start..end, "");
+                    self.pos = start;
+                    last = self.yank(buf, (n + 1) % self.session.kill_ring_len());
+                }
+            }
+            // History.
+            Key::Accelerator(A::Up) | Key::Chord(Ctrl, 'p') => {
+                let len = self.session.history(self.prompt).len();
+                let n = match self.history_pos {
+                    None => len.checked_sub(1),
+                    Some(n) => Some(n.saturating_sub(1)),
+                };
+                if n.is_some() {
+                    self.show_history(buf, n);
+                }
+            }
+            Key::Accelerator(A::Down) | Key::Chord(Ctrl, 'n') => {
+                if let Some(n) = self.history_pos {
+                    let len = self.session.history(self.prompt).len();
+                    self.show_history(buf, (n + 1 < len).then_some(n + 1));
+                }
+            }
+            // Go one screen width front or back.
+            Key::Accelerator(A::PgUp) => self.move_cols(buf, self.available_width, false),
+            Key::Accelerator(A::PgDn) => self.move_cols(buf, self.available_width, true),
+            // Switching between insert and replace mode.
+            Key::Accelerator(A::Insert) => self.is_insert = !self.is_insert,
+            // Spurious key input.
+            _ => {}
+        }
+        self.last = last;
+        false
+    }
+}
+
+/// List completion candidates under the line, in columns.
+fn list_candidates<T: ?Sized + Console>(
+    console: &mut T,
+    candidates: &[String],
+    cols: usize,
+) -> Result<()> {
+    let names: Vec<&str> = candidates
+        .iter()
+        .map(|x| complete::display_name(x))
+        .collect();
+    let column = names.iter().map(|x| width(x)).max().unwrap_or(0) + 2;
+    let per_line = (cols / column).max(1);
+    for (n, name) in names.iter().enumerate() {
+        if n % per_line == 0 {
+            console.write_char('\n').core_err()?;
+        }
+        console.write_str(name).core_err()?;
+        if n % per_line != per_line - 1 {
+            console
+                .write_str(&" ".repeat(column - width(name)))
+                .core_err()?;
+        }
+    }
+    console.write_char('\n').core_err()
+}
+
+/// Edit a line. Input is masked with `mask` if given.
+///
+/// With a completer, `Tab` completes the word before cursor.
+pub(super) fn edit_line<T: ?Sized + Console>(
+    console: &mut T,
+    buf: &mut String,
+    prompt: &str,
+    mask: Option<char>,
+    completer: Option<&mut dyn Complete>,
+) -> Result<i32> {
+    let size = console.terminal_info()?.size;
+    let prompt_width = width(prompt);
+    if size.0 < LINEEDIT_MIN_LENGTH || size.0 < prompt_width + LINEEDIT_MIN_LENGTH / 2 {
+        return Err(anyhow!(
+            "console too small, expected at least {} cols, got {}.",
+            LINEEDIT_MIN_LENGTH.max(prompt_width + LINEEDIT_MIN_LENGTH / 2),
+            size.0
+        ));
+    }
+    // Secrets are edited without history or kill ring.
+    let session = match (mask, console.edit_session()) {
+        (None, Some(session)) => core::mem::take(session),
+        _ => EditSession::default(),
+    };
+    let mut state = LineState {
+        pos: buf.len(),
+        left: 0,
+        is_insert: true,
+        // Last column is left out, as writing there scrolls some consoles.
+        available_width: size.0 - 1 - prompt_width,
+        mask,
+        prompt,
+        session,
+        history_pos: None,
+        draft: String::new(),
+        last: LastAction::Other,
+    };
+    // Session is put back even if editing fails.
+    let ret = edit_loop(console, buf, &mut state, size.0, completer);
+
+    if mask.is_none() {
+        if ret.is_ok() {
+            state.session.push_history(prompt, buf);
+        }
+        if let Some(session) = console.edit_session() {
+            *session = state.session;
+        }
+    }
+    console.write_char('\n').core_err()?;
+    console.set_cursor_style(&CursorStyle::None)?;
+    ret
+}
+
+/// Redraw and handle keys until the line is done.
+fn edit_loop<T: ?Sized + Console>(
+    console: &mut T,
+    buf: &mut String,
+    state: &mut LineState,
+    cols: usize,
+    mut completer: Option<&mut dyn Complete>,
+) -> Result<i32> {
+    let prompt = state.prompt;
+    let prompt_width = width(prompt);
+    console.set_cursor_style(&CursorStyle::Display)?;
+    console.write_str(prompt).core_err()?;
+    let mut line = console.get_cursor()?.1;
+
+    let mut redraw = true;
+    loop {
+        // Erase display area and redisplay.
+        if redraw {
+            state.follow_cursor(buf);
+            console.set_cursor(0, line)?;
+            console
+                .write_str(" ".repeat(cols - 1).as_str())
+                .core_err()?;
+            console.set_cursor(0, line)?;
+            console.write_str(prompt).core_err()?;
+            console
+                .write_str(&state.visible(buf))
+                .core_err()
+                .context("write")?;
+        }
+        let x = prompt_width + state.width(&buf[state.left..state.pos]);
+        console
+            .set_cursor(x as i32, line)
+            .with_context(|| alloc::format!("attempt to locate cursor to {}, {}\n", x, line))?;
+
+        let key = console.wait_for_key()?;
+        // Appending within visible range only needs the new char written.
+        let append = matches!(key, Key::Printable(k) if !k.is_control()) && state.pos == buf.len();
+        let old_left = state.left;
+        if let (Key::Accelerator(AcceleratorKey::Tab), Some(completer)) = (&key, &mut completer) {
+            let candidates = state.complete(buf, &mut **completer);
+            if !candidates.is_empty() {
+                // Prompt is redrawn under the list.
+                console.set_cursor(0, line)?;
+                list_candidates(console, &candidates, cols - 1)?;
+                line = console.get_cursor()?.1;
+            }
+            state.last = LastAction::Other;
+            redraw = true;
+            continue;
+        }
+        if state.handle(buf, key.clone()) {
+            return Ok(buf.chars().count() as i32);
+        }
+        state.follow_cursor(buf);
+        redraw = !(append && state.left == old_left);
+        if !redraw {
+            if let Key::Printable(k) = key {
+                console.write_char(state.mask.unwrap_or(k)).core_err()?;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockConsole;
+    use crate::LineEdit;
+
+    fn printable(s: &str) -> impl Iterator<Item = Key> + '_ {
+        s.chars().map(Key::Printable)
+    }
+
+    fn key(k: AcceleratorKey) -> Key {
+        Key::Accelerator(k)
+    }
+
+    fn edit(console: &mut MockConsole, default: &str, prompt: &str) -> String {
+        let mut buf = String::from(default);
+        console.keys.push_back(key(AcceleratorKey::Enter));
+        console.edit_line(&mut buf, prompt).unwrap();
+        buf
+    }
+
+    #[test]
+    fn width_counts_columns() {
+        assert_eq!(width("abc"), 3);
+        assert_eq!(width("中文"), 4);
+        assert_eq!(width("e\u{301}"), 1);
+    }
+
+    #[test]
+    fn insert_in_middle_of_non_ascii() {
+        let mut console = MockConsole::new(40, 5);
+        console.keys.extend(printable("héllo"));
+        console.keys.push_back(key(AcceleratorKey::Left));
+        console.keys.push_back(key(AcceleratorKey::Left));
+        console.keys.extend(printable("ü"));
+        assert_eq!(edit(&mut console, "", "> "), "hélülo");
+    }
+
+    #[test]
+    fn backspace_removes_whole_grapheme() {
+        let mut console = MockConsole::new(40, 5);
+        console.keys.push_back(key(AcceleratorKey::Backspace));
+        assert_eq!(edit(&mut console, "cafe\u{301}", "> "), "caf");
+    }
+
+    #[test]
+    fn delete_and_replace_wide_chars() {
+        let mut console = MockConsole::new(40, 5);
+        console.keys.push_back(key(AcceleratorKey::Home));
+        console.keys.push_back(key(AcceleratorKey::Delete));
+        console.keys.push_back(key(AcceleratorKey::Insert));
+        console.keys.extend(printable("日"));
+        assert_eq!(edit(&mut console, "中文字", "> "), "日字");
+    }
+
+    #[test]
+    fn cursor_uses_display_width() {
+        let mut console = MockConsole::new(40, 5);
+        console.keys.extend(printable("中文"));
+        assert_eq!(edit(&mut console, "", "> "), "中文");
+        // Cursor when Enter is read.
+        assert_eq!(console.cursor_log.last(), Some(&(2 + 4, 0)));
+        assert_eq!(console.row(0).trim_end(), "> 中文");
+    }
+
+    #[test]
+    fn long_wide_text_scrolls() {
+        let mut console = MockConsole::new(32, 5);
+        let text = "测试".repeat(40);
+        console.keys.extend(printable(&text));
+        assert_eq!(edit(&mut console, "", "密码: "), text);
+        let row = console.row(0);
+        assert!(row.starts_with("密码: "));
+        assert!(width(row.trim_end()) < 32);
+    }
+
+    #[test]
+    fn long_default_is_shown_from_end() {
+        let mut console = MockConsole::new(32, 5);
+        let text = "ä".repeat(100);
+        assert_eq!(edit(&mut console, &text, "> "), text);
+        assert_eq!(
+            console.row(0).trim_end(),
+            alloc::format!("> {}", "ä".repeat(28))
+        );
+    }
+
+    fn ctrl(c: char) -> Key {
+        Key::Chord(ModifierKey::Ctrl, c)
+    }
+
+    fn alt(c: char) -> Key {
+        Key::Chord(ModifierKey::Alt, c)
+    }
+
+    #[test]
+    fn emacs_motion() {
+        let mut console = MockConsole::new(40, 5);
+        console.keys.extend([ctrl('a'), ctrl('f'), ctrl('f')]);
+        console.keys.extend(printable("X"));
+        console.keys.extend([ctrl('e'), ctrl('b')]);
+        console.keys.extend(printable("Y"));
+        assert_eq!(edit(&mut console, "äbcd", "> "), "äbXcYd");
+    }
+
+    #[test]
+    fn word_motion() {
+        let mut console = MockConsole::new(40, 5);
+        console.keys.extend([alt('b'), alt('b')]);
+        console.keys.extend(printable("_"));
+        console.keys.extend([alt('f'), key(AcceleratorKey::Right)]);
+        console.keys.push_back(Key::ChordAccelerator(
+            ModifierKey::Ctrl,
+            AcceleratorKey::Right,
+        ));
+        console.keys.extend(printable("!"));
+        assert_eq!(
+            edit(&mut console, "root=/dev/sda1 quiet", "> "),
+            "root=/dev/_sda1 quiet!"
+        );
+    }
+
+    #[test]
+    fn kill_and_yank() {
+        let mut console = MockConsole::new(40, 5);
+        // Consecutive kills are joined.
+        console
+            .keys
+            .extend([ctrl('w'), ctrl('w'), ctrl('a'), ctrl('y')]);
+        assert_eq!(
+            edit(&mut console, "quiet splash nomodeset", "> "),
+            "splash nomodesetquiet "
+        );
+
+        let mut console = MockConsole::new(40, 5);
+        console.keys.extend([
+            ctrl('a'),
+            alt('d'),
+            ctrl('e'),
+            ctrl('u'),
+            ctrl('y'),
+            alt('y'),
+        ]);
+        assert_eq!(edit(&mut console, "one two", "> "), "one");
+    }
+
+    #[test]
+    fn kill_to_end() {
+        let mut console = MockConsole::new(40, 5);
+        console
+            .keys
+            .extend([ctrl('a'), alt('f'), ctrl('k'), ctrl('y'), ctrl('y')]);
+        assert_eq!(edit(&mut console, "中文 字符", "> "), "中文 字符 字符");
+    }
+
+    #[test]
+    fn history_is_per_prompt() {
+        let mut console = MockConsole::new(40, 5);
+        console.session = Some(EditSession::default());
+        edit(&mut console, "first", "Boot:");
+        edit(&mut console, "second", "Boot:");
+        edit(&mut console, "other", "cmdline: ");
+
+        console
+            .keys
+            .extend([key(AcceleratorKey::Up), key(AcceleratorKey::Up)]);
+        assert_eq!(edit(&mut console, "draft", "Boot:"), "first");
+
+        console.keys.extend(printable("new"));
+        console.keys.extend([ctrl('p'), ctrl('n'), ctrl('n')]);
+        assert_eq!(edit(&mut console, "", "Boot:"), "new");
+
+        console.keys.push_back(key(AcceleratorKey::Up));
+        assert_eq!(edit(&mut console, "", "cmdline: "), "other");
+    }
+
+    #[test]
+    fn secret_is_masked_and_forgotten() {
+        let mut console = MockConsole::new(40, 5);
+        console.session = Some(EditSession::default());
+        let mut buf = String::new();
+        console.keys.extend(printable("pässwörd"));
+        console
+            .keys
+            .extend([ctrl('u'), ctrl('y'), key(AcceleratorKey::Enter)]);
+        console.edit_secret(&mut buf, "Password: ").unwrap();
+        assert_eq!(buf, "");
+        assert_eq!(console.row(0).trim_end(), "Password:");
+
+        let mut buf = String::new();
+        console.keys.extend(printable("密码"));
+        console.keys.push_back(key(AcceleratorKey::Enter));
+        console.edit_secret(&mut buf, "Password: ").unwrap();
+        assert_eq!(buf, "密码");
+        assert_eq!(console.row(1).trim_end(), "Password: **");
+        assert!(console
+            .session
+            .as_ref()
+            .unwrap()
+            .history("Password: ")
+            .is_empty());
+    }
+
+    struct Words(&'static [&'static str]);
+
+    impl Complete for Words {
+        fn complete(&mut self, word: &str) -> Vec<String> {
+            self.0
+                .iter()
+                .filter(|x| x.starts_with(word))
+                .map(|x| String::from(*x))
+                .collect()
+        }
+    }
+
+    fn complete(
+        console: &mut MockConsole,
+        default: &str,
+        words: &'static [&'static str],
+    ) -> String {
+        let mut buf = String::from(default);
+        console.keys.push_back(key(AcceleratorKey::Enter));
+        console
+            .edit_line_complete(&mut buf, "> ", &mut Words(words))
+            .unwrap();
+        buf
+    }
+
+    #[test]
+    fn tab_completes_word_before_cursor() {
+        let mut console = MockConsole::new(40, 5);
+        console.keys.push_back(key(AcceleratorKey::Tab));
+        assert_eq!(
+            complete(&mut console, "ro qu", &["quiet", "root="]),
+            "ro quiet "
+        );
+
+        // Common prefix is inserted, without a space.
+        let mut console = MockConsole::new(40, 5);
+        console.keys.push_back(key(AcceleratorKey::Tab));
+        assert_eq!(
+            complete(&mut console, "r", &["rootflags=", "rootfstype="]),
+            "rootf"
+        );
+    }
+
+    #[test]
+    fn tab_lists_ambiguous_candidates() {
+        let mut console = MockConsole::new(40, 5);
+        console.keys.push_back(key(AcceleratorKey::Tab));
+        console.keys.extend(printable("w"));
+        assert_eq!(
+            complete(&mut console, "ro", &["ro", "root=", "rootwait"]),
+            "row"
+        );
+        assert_eq!(console.row(0).trim_end(), "> ro");
+        assert_eq!(console.row(1).trim_end(), "ro        root=     rootwait");
+        assert_eq!(console.row(2).trim_end(), "> row");
+    }
+}
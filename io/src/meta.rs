@@ -0,0 +1,153 @@
+use alloc::string::String;
+use anyhow::anyhow;
+use anyhow::Result;
+
+use super::area::{self, EditConfig};
+use super::complete::Complete;
+use super::console::{AcceleratorKey, Console, Key};
+use super::line;
+pub trait Handle: Sized {
+    /// Close current resource.
+    /// It's preferred to release resource in `trait drop`.
+    fn close(self) {
+        drop(self)
+    }
+}
+
+pub trait Stream {
+    fn read(&mut self, buf: &mut [u8]) -> Result<i32>;
+    fn write(&mut self, buf: &[u8]) -> Result<i32>;
+}
+
+pub trait Read<T> {
+    fn read(&mut self, buf: &mut [T]) -> Result<i32>;
+}
+
+/// Read one item of T.
+pub trait ReadOne<T> {
+    fn read_one(&mut self) -> Result<T>;
+}
+
+/// Read a line from terminal.
+///
+/// Does not provide line editing capability.
+/// Note: This trait is not for reading password.
+pub trait ReadString {
+    /// Read a line, with echo.
+    ///
+    /// Do not use this function for secrets!
+    /// Types of `Read<Key>` will automatically implement this trait.
+    fn read_line(&mut self, buf: &mut String) -> Result<i32>;
+}
+
+/// Read a line of secret (such as password) from terminal.
+///
+/// Input should be hidden or masked.
+pub trait ReadSecret {
+    /// Read a line, secretly and with no echo at all.
+    ///
+    /// For those who don't use *nix, this may cause confusion.
+    fn read_pass(&mut self, buf: &mut String) -> Result<i32>;
+
+    /// Same as read_pass(), which input is masked with `*`
+    ///
+    /// Common practice in most UI, but not commonly used in *nix.
+    fn read_pass_star(&mut self, buf: &mut String) -> Result<i32>;
+}
+
+impl<T: ?Sized + ReadOne<Key> + core::fmt::Write> ReadString for T {
+    fn read_line(&mut self, buf: &mut String) -> Result<i32> {
+        buf.clear();
+        let mut n = 0;
+        loop {
+            let i = self.read_one()?;
+            match i {
+                Key::Printable(k) => {
+                    n += 1;
+                    buf.push(k);
+                    self.write_char(k)
+                        .map_err(|_| anyhow!("Failed to write to console."))?;
+                }
+                Key::Accelerator(AcceleratorKey::Enter) => {
+                    self.write_str(if cfg!(target_os = "uefi") {
+                        "\r\n"
+                    } else {
+                        "\n"
+                    })
+                    .map_err(|_| anyhow!("Failed to write to console."))?;
+                    return Ok(n);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+pub trait Write<T> {
+    fn write(&mut self, buf: &[T]) -> Result<i32>;
+}
+
+pub trait WriteString {
+    fn write_str(&mut self, buf: &str) -> Result<i32>;
+}
+
+/// Block device meta-interface.
+///
+/// Note: this trait only support offset within 2^31-1 (2GB). This should be enough for bootloaders.
+pub trait BlockDevice: Read<u8> {
+    fn get_pos(&self) -> Result<i32>;
+    fn set_pos(&mut self, pos: i32) -> Result<i32>;
+}
+
+/// Console with line edit capability.
+pub trait LineEdit {
+    /// Read a cleartext line.
+    /// The line is editable, and may have default value.
+    ///
+    /// Returns string length.
+    fn edit_line(&mut self, buf: &mut String, prompt: &str) -> Result<i32>;
+
+    /// Read a secret line, such as password.
+    ///
+    /// Input is masked with `*`, and kept out of history and kill ring.
+    fn edit_secret(&mut self, buf: &mut String, prompt: &str) -> Result<i32>;
+
+    /// Read a cleartext line, completing the word before cursor with `completer` on `Tab`.
+    fn edit_line_complete(
+        &mut self,
+        buf: &mut String,
+        prompt: &str,
+        completer: &mut dyn Complete,
+    ) -> Result<i32>;
+
+    /// Edit an area of text.
+    ///
+    /// Returns `true` if the text is saved, and `false` if the edit is cancelled.
+    /// `buf` is only changed when saved.
+    fn edit_area(&mut self, _buf: &mut String, _config: &EditConfig) -> Result<bool> {
+        Err(anyhow!("Not supported."))
+    }
+}
+
+impl<T: ?Sized + Console> LineEdit for T {
+    fn edit_line(&mut self, buf: &mut String, prompt: &str) -> Result<i32> {
+        line::edit_line(self, buf, prompt, None, None)
+    }
+
+    fn edit_secret(&mut self, buf: &mut String, prompt: &str) -> Result<i32> {
+        line::edit_line(self, buf, prompt, Some('*'), None)
+    }
+
+    fn edit_line_complete(
+        &mut self,
+        buf: &mut String,
+        prompt: &str,
+        completer: &mut dyn Complete,
+    ) -> Result<i32> {
+        line::edit_line(self, buf, prompt, None, Some(completer))
+    }
+
+    fn edit_area(&mut self, buf: &mut String, config: &EditConfig) -> Result<bool> {
+        area::edit_area(self, buf, config)
+    }
+}
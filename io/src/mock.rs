@@ -0,0 +1,117 @@
+//! Console for tests.
+
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use anyhow::{anyhow, Result};
+use unicode_width::UnicodeWidthChar;
+
+use super::console::{Console, Key, TerminalInfo};
+use super::{EditSession, ReadOne};
+
+/// A console with a screen buffer, reading keys from a queue.
+pub struct MockConsole {
+    /// Screen cells. The cell after a wide char is `'\0'`.
+    pub screen: Vec<Vec<char>>,
+    pub cursor: (i32, i32),
+    pub size: (usize, usize),
+    /// Keys to be read.
+    pub keys: VecDeque<Key>,
+    /// Cursor position when each key is read.
+    pub cursor_log: Vec<(i32, i32)>,
+    pub session: Option<EditSession>,
+}
+
+impl MockConsole {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            screen: vec![vec![' '; width]; height],
+            cursor: (0, 0),
+            size: (width, height),
+            keys: VecDeque::new(),
+            cursor_log: Vec::new(),
+            session: None,
+        }
+    }
+
+    /// Move to next line, scrolling up at the bottom.
+    fn newline(&mut self) {
+        if self.cursor.1 as usize + 1 == self.size.1 {
+            self.screen.remove(0);
+            self.screen.push(vec![' '; self.size.0]);
+            self.cursor.0 = 0;
+        } else {
+            self.cursor = (0, self.cursor.1 + 1);
+        }
+    }
+
+    /// Text on a screen row.
+    pub fn row(&self, y: usize) -> String {
+        self.screen[y].iter().filter(|x| **x != '\0').collect()
+    }
+}
+
+impl core::fmt::Write for MockConsole {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for c in s.chars() {
+            let (x, y) = (self.cursor.0 as usize, self.cursor.1 as usize);
+            match c {
+                '\n' => self.newline(),
+                '\r' => self.cursor.0 = 0,
+                c => {
+                    let w = c.width().unwrap_or(0);
+                    if w == 0 {
+                        continue;
+                    }
+                    let row = self.screen.get_mut(y).ok_or(core::fmt::Error)?;
+                    *row.get_mut(x).ok_or(core::fmt::Error)? = c;
+                    if w == 2 {
+                        *row.get_mut(x + 1).ok_or(core::fmt::Error)? = '\0';
+                    }
+                    self.cursor.0 += w as i32;
+                    if self.cursor.0 as usize >= self.size.0 {
+                        self.newline();
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ReadOne<Key> for MockConsole {
+    fn read_one(&mut self) -> Result<Key> {
+        self.cursor_log.push(self.cursor);
+        self.keys
+            .pop_front()
+            .ok_or_else(|| anyhow!("no more keys."))
+    }
+}
+
+impl Console for MockConsole {
+    fn get_cursor(&mut self) -> Result<(i32, i32)> {
+        Ok(self.cursor)
+    }
+
+    fn set_cursor(&mut self, x: i32, y: i32) -> Result<()> {
+        if x < 0 || y < 0 || x as usize >= self.size.0 || y as usize >= self.size.1 {
+            return Err(anyhow!("cursor out of screen: {}, {}", x, y));
+        }
+        self.cursor = (x, y);
+        Ok(())
+    }
+
+    fn edit_session(&mut self) -> Option<&mut EditSession> {
+        self.session.as_mut()
+    }
+
+    fn terminal_info(&mut self) -> Result<TerminalInfo> {
+        Ok(TerminalInfo { size: self.size })
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        *self = Self::new(self.size.0, self.size.1);
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+//! Editing state kept across prompts in a session.
+
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+const HISTORY_MAX: usize = 32;
+const KILL_RING_MAX: usize = 16;
+
+/// History and kill ring of line editing.
+///
+/// Consoles keep one of this, so it lives as long as the console.
+/// Nothing here is saved, so it is gone after reboot.
+#[derive(Default)]
+pub struct EditSession {
+    /// Entered lines, per prompt. Newest is at the end.
+    history: BTreeMap<String, Vec<String>>,
+    /// Killed text. Newest is at the front.
+    kill_ring: VecDeque<String>,
+}
+
+impl EditSession {
+    /// History of a prompt.
+    pub fn history(&self, prompt: &str) -> &[String] {
+        self.history
+            .get(prompt)
+            .map(|x| x.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Add an entered line to history of a prompt.
+    ///
+    /// Empty lines and repeats of the last line are not recorded.
+    pub fn push_history(&mut self, prompt: &str, line: &str) {
+        if line.is_empty() {
+            return;
+        }
+        let history = self.history.entry(String::from(prompt)).or_default();
+        if history.last().is_some_and(|x| x == line) {
+            return;
+        }
+        if history.len() == HISTORY_MAX {
+            history.remove(0);
+        }
+        history.push(String::from(line));
+    }
+
+    /// Put killed text into the ring.
+    ///
+    /// If `append` is set, the text is joined to the newest entry instead,
+    /// at the end if `forward`, or at the front otherwise.
+    pub fn kill(&mut self, text: &str, append: bool, forward: bool) {
+        if text.is_empty() {
+            return;
+        }
+        match self.kill_ring.front_mut() {
+            Some(last) if append => {
+                if forward {
+                    last.push_str(text);
+                } else {
+                    last.insert_str(0, text);
+                }
+            }
+            _ => {
+                if self.kill_ring.len() == KILL_RING_MAX {
+                    self.kill_ring.pop_back();
+                }
+                self.kill_ring.push_front(String::from(text));
+            }
+        }
+    }
+
+    /// Killed text, `0` being the newest.
+    pub fn killed(&self, n: usize) -> Option<&str> {
+        self.kill_ring.get(n).map(|x| x.as_str())
+    }
+
+    pub fn kill_ring_len(&self) -> usize {
+        self.kill_ring.len()
+    }
+}
@@ -0,0 +1,548 @@
+//! Multiboot2 image loader.
+//!
+//! Header of image, where its parts are loaded, and boot information given to it, independent of
+//! firmware. See <https://www.gnu.org/software/grub/manual/multiboot2/multiboot.html>.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use anyhow::{anyhow, Result};
+use core::ops::Range;
+use thiserror::Error;
+
+/// Magic of the header in image.
+pub const HEADER_MAGIC: u32 = 0xE852_50D6;
+/// Magic passed to the kernel with boot information.
+pub const BOOTLOADER_MAGIC: u32 = 0x36D7_6289;
+/// The header must be in the first 32 KiB of image, aligned to 8 bytes.
+const SEARCH_LIMIT: usize = 32768;
+/// Architecture of header for 32-bit protected mode of i386, which x86_64 kernels use too.
+const ARCH_I386: u32 = 0;
+
+/// Types of boot information tags.
+pub mod tag {
+    pub const END: u32 = 0;
+    pub const CMDLINE: u32 = 1;
+    pub const BOOT_LOADER_NAME: u32 = 2;
+    pub const MODULE: u32 = 3;
+    pub const MMAP: u32 = 6;
+    pub const FRAMEBUFFER: u32 = 8;
+    pub const EFI64: u32 = 12;
+    pub const ACPI_OLD: u32 = 14;
+    pub const ACPI_NEW: u32 = 15;
+    pub const EFI_MMAP: u32 = 17;
+    pub const EFI_BS: u32 = 18;
+    pub const EFI64_IH: u32 = 20;
+}
+
+/// Types of `MMAP` entries.
+pub mod memory {
+    pub const AVAILABLE: u32 = 1;
+    pub const RESERVED: u32 = 2;
+    pub const ACPI_RECLAIMABLE: u32 = 3;
+    pub const NVS: u32 = 4;
+    pub const BADRAM: u32 = 5;
+}
+
+fn get<const N: usize>(data: &[u8], offset: usize) -> Option<[u8; N]> {
+    data.get(offset..offset.checked_add(N)?)?.try_into().ok()
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    get(data, offset).map(u16::from_le_bytes)
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    get(data, offset).map(u32::from_le_bytes)
+}
+
+fn u64_at(data: &[u8], offset: usize) -> Option<u64> {
+    get(data, offset).map(u64::from_le_bytes)
+}
+
+fn align8(x: usize) -> usize {
+    (x + 7) & !7
+}
+
+/// Where an image which is not ELF is loaded, given by the address tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddressTag {
+    /// Address the header is loaded at.
+    pub header_addr: u32,
+    pub load_addr: u32,
+    /// End of data loaded, 0 for the whole image.
+    pub load_end_addr: u32,
+    /// End of bss zeroed after data, 0 for no bss.
+    pub bss_end_addr: u32,
+}
+
+/// Multiboot2 header of a kernel image.
+#[derive(Debug, Default)]
+pub struct Header {
+    /// Offset of header in image.
+    pub offset: usize,
+    /// Types of information the kernel asks for.
+    pub requests: Vec<u32>,
+    /// The kernel can boot without information it asks for.
+    pub requests_optional: bool,
+    pub address: Option<AddressTag>,
+    /// Entry in 32-bit protected mode.
+    pub entry: Option<u32>,
+    /// Entry in 64-bit mode with boot services running, used with `efi_boot_services`.
+    pub efi_amd64_entry: Option<u32>,
+    /// The kernel can be started without exiting boot services.
+    pub efi_boot_services: bool,
+    /// Preferred framebuffer width, height and depth, each 0 for no preference.
+    pub framebuffer: Option<(u32, u32, u32)>,
+}
+
+impl Header {
+    /// Find and parse the header of `image`.
+    pub fn parse(image: &[u8]) -> Result<Header> {
+        let offset = (0..image.len().min(SEARCH_LIMIT))
+            .step_by(8)
+            .find(|&x| Self::valid_at(image, x))
+            .ok_or_else(|| anyhow!("no multiboot2 header is found."))?;
+        let arch = u32_at(image, offset + 4).unwrap_or_default();
+        if arch != ARCH_I386 {
+            return Err(anyhow!("multiboot2 header is for architecture {}.", arch));
+        }
+        let end = offset + u32_at(image, offset + 8).unwrap_or_default() as usize;
+        let mut ret = Header {
+            offset,
+            ..Default::default()
+        };
+        let mut pos = offset + 16;
+        while pos + 8 <= end {
+            let ty = u16_at(image, pos).unwrap_or_default();
+            let optional = u16_at(image, pos + 2).unwrap_or_default() & 1 != 0;
+            let size = u32_at(image, pos + 4).unwrap_or_default() as usize;
+            if size < 8 || pos + size > end {
+                return Err(anyhow!("multiboot2 header tag {} is broken.", ty));
+            }
+            let tag = &image[pos..pos + size];
+            let field = |n: usize| {
+                u32_at(tag, 8 + 4 * n)
+                    .ok_or_else(|| anyhow!("multiboot2 header tag {} is too short.", ty))
+            };
+            match ty {
+                0 => break,
+                1 => {
+                    ret.requests = (8..size)
+                        .step_by(4)
+                        .filter_map(|x| u32_at(tag, x))
+                        .collect();
+                    ret.requests_optional = optional;
+                }
+                2 => {
+                    ret.address = Some(AddressTag {
+                        header_addr: field(0)?,
+                        load_addr: field(1)?,
+                        load_end_addr: field(2)?,
+                        bss_end_addr: field(3)?,
+                    })
+                }
+                3 => ret.entry = Some(field(0)?),
+                5 => ret.framebuffer = Some((field(0)?, field(1)?, field(2)?)),
+                7 => ret.efi_boot_services = true,
+                9 => ret.efi_amd64_entry = Some(field(0)?),
+                // Console flags, module alignment and relocation are met as modules are loaded
+                // to pages, and the kernel to where it is linked. Entry of EFI i386 is unused.
+                4 | 6 | 8 | 10 => {}
+                _ if !optional => {
+                    return Err(anyhow!("multiboot2 header tag {} is unsupported.", ty));
+                }
+                _ => {}
+            }
+            pos += align8(size);
+        }
+        Ok(ret)
+    }
+
+    fn valid_at(image: &[u8], offset: usize) -> bool {
+        let words: Option<[u32; 4]> = (|| {
+            Some([
+                u32_at(image, offset)?,
+                u32_at(image, offset + 4)?,
+                u32_at(image, offset + 8)?,
+                u32_at(image, offset + 12)?,
+            ])
+        })();
+        let Some([magic, arch, length, checksum]) = words else {
+            return false;
+        };
+        magic == HEADER_MAGIC
+            && length >= 16
+            && offset + length as usize <= image.len()
+            && magic
+                .wrapping_add(arch)
+                .wrapping_add(length)
+                .wrapping_add(checksum)
+                == 0
+    }
+
+    /// Type of information the kernel requires, which is not in `provided`.
+    pub fn missing(&self, provided: impl Fn(u32) -> bool) -> Option<u32> {
+        if self.requests_optional {
+            return None;
+        }
+        self.requests.iter().copied().find(|&x| !provided(x))
+    }
+
+    /// Parts of `image` to load, and entry of ELF.
+    pub fn layout(&self, image: &[u8]) -> Result<(Vec<Segment>, Option<u64>)> {
+        match self.address {
+            Some(address) => Ok((Vec::from([self.address_segment(address, image)?]), None)),
+            None => elf_segments(image).map(|(segments, entry)| (segments, Some(entry))),
+        }
+    }
+
+    fn address_segment(&self, address: AddressTag, image: &[u8]) -> Result<Segment> {
+        let broken = || anyhow!("multiboot2 address tag is out of image.");
+        let start = address
+            .header_addr
+            .checked_sub(address.load_addr)
+            .and_then(|x| self.offset.checked_sub(x as usize))
+            .ok_or_else(broken)?;
+        let end = match address.load_end_addr {
+            0 => image.len(),
+            end => end
+                .checked_sub(address.load_addr)
+                .map(|x| start + x as usize)
+                .filter(|&x| x <= image.len())
+                .ok_or_else(broken)?,
+        };
+        let size = match address.bss_end_addr {
+            0 => (end - start) as u64,
+            bss_end => bss_end
+                .checked_sub(address.load_addr)
+                .map(u64::from)
+                .filter(|&x| x >= (end - start) as u64)
+                .ok_or_else(broken)?,
+        };
+        Ok(Segment {
+            addr: address.load_addr.into(),
+            data: start..end,
+            size,
+        })
+    }
+}
+
+/// A part of image loaded to memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    /// Physical address it is loaded to.
+    pub addr: u64,
+    /// Range of image copied to `addr`.
+    pub data: Range<usize>,
+    /// Size in memory, which is zeroed after data.
+    pub size: u64,
+}
+
+impl Segment {
+    /// Address after the segment in memory.
+    pub fn end(&self) -> Result<u64> {
+        self.addr
+            .checked_add(self.size)
+            .ok_or_else(|| anyhow!("segment at {:#x} ends out of memory.", self.addr))
+    }
+}
+
+/// Loadable segments of an ELF image, at their physical addresses, and its physical entry.
+fn elf_segments(image: &[u8]) -> Result<(Vec<Segment>, u64)> {
+    let broken = || anyhow!("image is neither ELF nor has a multiboot2 address tag.");
+    if image.get(..4) != Some(b"\x7fELF") || image.get(5) != Some(&1) {
+        return Err(broken());
+    }
+    let elf64 = match image.get(4) {
+        Some(1) => false,
+        Some(2) => true,
+        _ => return Err(broken()),
+    };
+    let word = |offset: usize| match elf64 {
+        true => u64_at(image, offset),
+        false => u32_at(image, offset).map(u64::from),
+    };
+    // Offsets of entry, program headers, and each field of a program header.
+    let (entry, phoff, phentsize, phnum, fields) = match elf64 {
+        true => (24, 32, 54, 56, [8, 16, 24, 32, 40]),
+        false => (24, 28, 42, 44, [4, 8, 12, 16, 20]),
+    };
+    let entry = word(entry).ok_or_else(broken)?;
+    let phoff = word(phoff).ok_or_else(broken)? as usize;
+    let phentsize = u16_at(image, phentsize).ok_or_else(broken)? as usize;
+    let phnum = u16_at(image, phnum).ok_or_else(broken)? as usize;
+    let mut segments = Vec::new();
+    let mut physical_entry = None;
+    for n in 0..phnum {
+        let out = || anyhow!("ELF segment {} is out of image.", n);
+        let header = n
+            .checked_mul(phentsize)
+            .and_then(|x| x.checked_add(phoff))
+            .ok_or_else(out)?;
+        // Only PT_LOAD is loaded.
+        if u32_at(image, header) != Some(1) {
+            continue;
+        }
+        let [offset, vaddr, paddr, filesz, memsz] =
+            fields.map(|x| word(header + x).ok_or_else(broken));
+        let (offset, vaddr, paddr, filesz, memsz) = (offset?, vaddr?, paddr?, filesz?, memsz?);
+        let end = offset
+            .checked_add(filesz)
+            .and_then(|x| usize::try_from(x).ok())
+            .filter(|&x| x <= image.len() && filesz <= memsz)
+            .ok_or_else(out)?;
+        let vaddr_end = vaddr.checked_add(memsz).ok_or_else(out)?;
+        // Entry is virtual, and is moved with the segment it is in.
+        if (vaddr..vaddr_end).contains(&entry) {
+            physical_entry = Some((entry - vaddr).checked_add(paddr).ok_or_else(out)?);
+        }
+        let segment = Segment {
+            addr: paddr,
+            data: offset as usize..end,
+            size: memsz,
+        };
+        segment.end().map_err(|_| out())?;
+        segments.push(segment);
+    }
+    Ok((segments, physical_entry.unwrap_or(entry)))
+}
+
+/// Boot information does not fit in its buffer.
+#[derive(Debug, Error)]
+#[error("boot information is too large.")]
+pub struct TooLarge;
+
+/// Boot information written into a buffer allocated beforehand, as nothing can be allocated
+/// after boot services are exited.
+pub struct InfoWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+    /// Types of tags written, as bits.
+    written: u64,
+}
+
+impl<'a> InfoWriter<'a> {
+    /// Write into `buf`, which should be aligned to 8 bytes.
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self {
+            buf,
+            len: 8,
+            written: 0,
+        }
+    }
+
+    /// Whether a tag of `ty` has been written.
+    pub fn has(&self, ty: u32) -> bool {
+        ty < 64 && self.written & (1 << ty) != 0
+    }
+
+    /// Start a tag of `ty`, and return where it starts, for `end`.
+    pub fn begin(&mut self, ty: u32) -> Result<usize, TooLarge> {
+        let start = self.len;
+        self.push(&ty.to_le_bytes())?;
+        self.push(&[0; 4])?;
+        if ty < 64 {
+            self.written |= 1 << ty;
+        }
+        Ok(start)
+    }
+
+    /// Append `data` to the tag begun.
+    pub fn push(&mut self, data: &[u8]) -> Result<(), TooLarge> {
+        let end = self.len + data.len();
+        self.buf
+            .get_mut(self.len..end)
+            .ok_or(TooLarge)?
+            .copy_from_slice(data);
+        self.len = end;
+        Ok(())
+    }
+
+    /// End the tag begun at `start`, padding it to 8 bytes.
+    pub fn end(&mut self, start: usize) -> Result<(), TooLarge> {
+        let size = (self.len - start) as u32;
+        self.buf[start + 4..start + 8].copy_from_slice(&size.to_le_bytes());
+        let aligned = align8(self.len);
+        self.buf.get_mut(self.len..aligned).ok_or(TooLarge)?.fill(0);
+        self.len = aligned;
+        Ok(())
+    }
+
+    /// Write a tag of `ty` with `parts` as its content.
+    pub fn tag(&mut self, ty: u32, parts: &[&[u8]]) -> Result<(), TooLarge> {
+        // Checked first, so a tag is not left half written.
+        let size = 8 + parts.iter().map(|x| x.len()).sum::<usize>();
+        if align8(self.len + size) > self.buf.len() {
+            return Err(TooLarge);
+        }
+        let start = self.begin(ty)?;
+        for i in parts {
+            self.push(i)?;
+        }
+        self.end(start)
+    }
+
+    /// Write a tag of a string, such as cmdline.
+    pub fn string(&mut self, ty: u32, text: &str) -> Result<(), TooLarge> {
+        self.tag(ty, &[text.as_bytes(), &[0]])
+    }
+
+    /// Write a module loaded from `start` to `end`.
+    pub fn module(&mut self, start: u32, end: u32, cmdline: &str) -> Result<(), TooLarge> {
+        self.tag(
+            tag::MODULE,
+            &[
+                &start.to_le_bytes(),
+                &end.to_le_bytes(),
+                cmdline.as_bytes(),
+                &[0],
+            ],
+        )
+    }
+
+    /// Finish with the end tag, and return the size of information.
+    pub fn finish(mut self) -> Result<usize, TooLarge> {
+        self.tag(tag::END, &[])?;
+        let size = self.len as u32;
+        self.buf[..4].copy_from_slice(&size.to_le_bytes());
+        self.buf[4..8].fill(0);
+        Ok(self.len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Header with `tags`, each as type, flags and fields.
+    fn header(tags: &[(u16, u16, &[u32])]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (ty, flags, fields) in tags.iter().chain([&(0, 0, &[][..])]) {
+            let size = 8 + 4 * fields.len();
+            body.extend(ty.to_le_bytes());
+            body.extend(flags.to_le_bytes());
+            body.extend((size as u32).to_le_bytes());
+            fields.iter().for_each(|x| body.extend(x.to_le_bytes()));
+            body.resize(align8(body.len()), 0);
+        }
+        let length = 16 + body.len() as u32;
+        let checksum = 0u32
+            .wrapping_sub(HEADER_MAGIC)
+            .wrapping_sub(ARCH_I386)
+            .wrapping_sub(length);
+        let mut ret = Vec::from([0; 8]);
+        for i in [HEADER_MAGIC, ARCH_I386, length, checksum] {
+            ret.extend(i.to_le_bytes());
+        }
+        ret.extend(body);
+        ret
+    }
+
+    #[test]
+    fn address_tag() {
+        let mut image = header(&[
+            (2, 0, &[0x10_0008, 0x10_0000, 0, 0x10_2000]),
+            (3, 0, &[0x10_0040]),
+        ]);
+        image.resize(0x1000, 0);
+        let header = Header::parse(&image).unwrap();
+        assert_eq!(header.offset, 8);
+        assert_eq!(header.entry, Some(0x10_0040));
+        let (segments, entry) = header.layout(&image).unwrap();
+        assert_eq!(entry, None);
+        assert_eq!(
+            segments,
+            [Segment {
+                addr: 0x10_0000,
+                data: 0..0x1000,
+                size: 0x2000
+            }]
+        );
+    }
+
+    #[test]
+    fn header_tags() {
+        let image = header(&[
+            (1, 0, &[tag::EFI_MMAP, 16]),
+            (7, 0, &[]),
+            (9, 0, &[0x20_0000]),
+        ]);
+        let parsed = Header::parse(&image).unwrap();
+        assert!(parsed.efi_boot_services);
+        assert_eq!(parsed.efi_amd64_entry, Some(0x20_0000));
+        assert_eq!(parsed.missing(|x| x == tag::EFI_MMAP), Some(16));
+        // Unknown tags fail only if they are not optional.
+        assert!(Header::parse(&header(&[(42, 1, &[])])).is_ok());
+        assert!(Header::parse(&header(&[(42, 0, &[])])).is_err());
+        let mut broken = image.clone();
+        broken[20] ^= 1;
+        assert!(Header::parse(&broken).is_err());
+    }
+
+    #[test]
+    fn elf_entry() {
+        // ELF header, one program header at 0x40, then multiboot2 header.
+        let mut image = Vec::from([0; 0x78]);
+        image[..8].copy_from_slice(b"\x7fELF\x02\x01\x01\x00");
+        image[24..32].copy_from_slice(&0xffff_8000_0010_0010u64.to_le_bytes());
+        image[32..40].copy_from_slice(&0x40u64.to_le_bytes());
+        image[54..56].copy_from_slice(&56u16.to_le_bytes());
+        image[56..58].copy_from_slice(&1u16.to_le_bytes());
+        let phdr = [1, 0, 0xffff_8000_0010_0000, 0x10_0000, 0x78, 0x1000u64];
+        for (n, value) in phdr.into_iter().enumerate() {
+            image[0x40 + 8 * n..0x48 + 8 * n].copy_from_slice(&value.to_le_bytes());
+        }
+        image.extend(&header(&[])[8..]);
+        let parsed = Header::parse(&image).unwrap();
+        assert_eq!(parsed.offset, 0x78);
+        let (segments, entry) = parsed.layout(&image).unwrap();
+        assert_eq!(entry, Some(0x10_0010));
+        assert_eq!(segments[0].addr, 0x10_0000);
+        assert_eq!(segments[0].data, 0..0x78);
+    }
+
+    #[test]
+    fn elf_overflow() {
+        let mut image = Vec::from([0; 0x78]);
+        image[..8].copy_from_slice(b"\x7fELF\x02\x01\x01\x00");
+        image[54..56].copy_from_slice(&56u16.to_le_bytes());
+        image[56..58].copy_from_slice(&2u16.to_le_bytes());
+        // Program headers wrap around after the first one.
+        image[32..40].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(elf_segments(&image).is_err());
+        // A segment ending beyond the address space.
+        image[32..40].copy_from_slice(&0x40u64.to_le_bytes());
+        image[56..58].copy_from_slice(&1u16.to_le_bytes());
+        let phdr = [1, 0, u64::MAX - 0x10, u64::MAX - 0x10, 0, 0x100u64];
+        for (n, value) in phdr.into_iter().enumerate() {
+            image[0x40 + 8 * n..0x48 + 8 * n].copy_from_slice(&value.to_le_bytes());
+        }
+        assert!(elf_segments(&image).is_err());
+        let segment = Segment {
+            addr: u64::MAX,
+            data: 0..0,
+            size: 1,
+        };
+        assert!(segment.end().is_err());
+    }
+
+    #[test]
+    fn info() {
+        let mut buf = [0u8; 64];
+        let mut info = InfoWriter::new(&mut buf);
+        info.string(tag::CMDLINE, "quiet").unwrap();
+        assert!(info.has(tag::CMDLINE));
+        assert!(info
+            .module(0x1000, 0x2000, "console=ttyS0,115200 quiet")
+            .is_err());
+        let size = info.finish().unwrap();
+        assert_eq!(size, 32);
+        assert_eq!(&buf[..4], &32u32.to_le_bytes());
+        assert_eq!(&buf[8..16], &[1, 0, 0, 0, 14, 0, 0, 0]);
+        assert_eq!(&buf[16..22], b"quiet\0");
+        assert_eq!(&buf[24..32], &[0, 0, 0, 0, 8, 0, 0, 0]);
+    }
+}
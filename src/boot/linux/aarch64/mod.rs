@@ -0,0 +1,24 @@
+//! Linux arm64 Image.
+
+use anyhow::{anyhow, Result};
+
+/// Magic of arm64 Image header, at offset 56.
+const MAGIC: &[u8; 4] = b"ARM\x64";
+/// Type of compressed kernels of `CONFIG_EFI_ZBOOT`, at offset 4, which decompress themselves.
+const ZBOOT: &[u8; 4] = b"zimg";
+
+/// Check `image` is an arm64 Image with EFI stub, which is a PE/COFF image as well.
+pub fn check_image(image: &[u8]) -> Result<()> {
+    if image.starts_with(&[0x1f, 0x8b]) {
+        return Err(anyhow!(
+            "kernel is compressed by gzip, which EFI stub can not boot. Use the uncompressed Image."
+        ));
+    }
+    if image.get(4..8) != Some(ZBOOT) && image.get(56..60) != Some(MAGIC) {
+        return Err(anyhow!("kernel is not an arm64 Linux Image."));
+    }
+    if !image.starts_with(b"MZ") {
+        return Err(anyhow!("kernel is built without EFI stub."));
+    }
+    Ok(())
+}
@@ -1,3 +1,12 @@
 //! Linux image loader.
+//!
+//! Kernels are booted through their EFI stub, and each architecture checks its image has one.
+#[cfg(target_arch = "aarch64")]
+pub mod aarch64;
 #[cfg(target_arch = "x86_64")]
 pub mod x86_64;
+
+#[cfg(target_arch = "aarch64")]
+pub use aarch64::check_image;
+#[cfg(target_arch = "x86_64")]
+pub use x86_64::check_image;
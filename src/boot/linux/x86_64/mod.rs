@@ -1 +1,17 @@
+//! Linux x86 bzImage.
 
+use anyhow::{anyhow, Result};
+
+/// Magic of the setup header, at offset 0x202.
+const MAGIC: &[u8; 4] = b"HdrS";
+
+/// Check `image` is a bzImage with EFI stub, which is a PE/COFF image as well.
+pub fn check_image(image: &[u8]) -> Result<()> {
+    if image.get(0x202..0x206) != Some(MAGIC) {
+        return Err(anyhow!("kernel is not a Linux bzImage."));
+    }
+    if !image.starts_with(b"MZ") {
+        return Err(anyhow!("kernel is built without EFI stub."));
+    }
+    Ok(())
+}
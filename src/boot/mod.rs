@@ -1,3 +1,5 @@
 /// Boot Image Loader.
 pub mod boot;
 pub mod linux;
+#[cfg(target_arch = "x86_64")]
+pub mod multiboot2;
@@ -0,0 +1,108 @@
+//! Entering multiboot2 kernels on x86_64.
+
+use core::arch::{asm, global_asm};
+use core::ptr::addr_of;
+
+use multiboot2::BOOTLOADER_MAGIC;
+
+// Leave long mode to 32-bit protected mode without paging, and jump to `%edi` with `%esi` as
+// boot information. It is copied to a page below 4 GiB, as it runs there in compatibility mode,
+// and takes the GDT pointer in `%rdx`, with 32-bit code at 0x08 and data at 0x10.
+global_asm!(
+    ".global b2_multiboot2_i386",
+    ".global b2_multiboot2_i386_end",
+    "b2_multiboot2_i386:",
+    "cli",
+    "lgdt (%rdx)",
+    "leaq 2f(%rip), %rax",
+    "pushq $0x08",
+    "pushq %rax",
+    "lretq",
+    ".code32",
+    "2:",
+    "movw $0x10, %ax",
+    "movw %ax, %ds",
+    "movw %ax, %es",
+    "movw %ax, %fs",
+    "movw %ax, %gs",
+    "movw %ax, %ss",
+    // Disable paging, then long mode in EFER, then PAE.
+    "movl %cr0, %eax",
+    "andl $0x7fffffff, %eax",
+    "movl %eax, %cr0",
+    "movl $0xc0000080, %ecx",
+    "rdmsr",
+    "andl $0xfffffeff, %eax",
+    "wrmsr",
+    "movl %cr4, %eax",
+    "andl $0xffffffdf, %eax",
+    "movl %eax, %cr4",
+    "movl $0x36d76289, %eax",
+    "movl %esi, %ebx",
+    "jmp *%edi",
+    "b2_multiboot2_i386_end:",
+    ".code64",
+    options(att_syntax)
+);
+
+extern "C" {
+    static b2_multiboot2_i386: u8;
+    static b2_multiboot2_i386_end: u8;
+}
+
+/// Offset of GDT in the low page, after code.
+const GDT: usize = 0x100;
+/// Null, flat 32-bit code and flat 32-bit data descriptors.
+const DESCRIPTORS: [u64; 3] = [0, 0x00CF_9A00_0000_FFFF, 0x00CF_9200_0000_FFFF];
+
+/// Enter the kernel at `entry` in 32-bit protected mode, as multiboot2 does on i386.
+///
+/// `low` is a page below 4 GiB, which the switch from long mode runs in.
+///
+/// # Safety
+///
+/// Boot services must have been exited, and the kernel and `info` loaded.
+pub unsafe fn enter_i386(entry: u32, info: u32, low: u32) -> ! {
+    let start = addr_of!(b2_multiboot2_i386);
+    let len = addr_of!(b2_multiboot2_i386_end) as usize - start as usize;
+    assert!(len <= GDT);
+    let page = low as usize as *mut u8;
+    core::ptr::copy_nonoverlapping(start, page, len);
+    let gdt = page.add(GDT);
+    core::ptr::copy_nonoverlapping(DESCRIPTORS.as_ptr().cast::<u8>(), gdt, 24);
+    // Pointer of GDT, as limit and base.
+    let pointer = gdt.add(24);
+    pointer.cast::<u16>().write_unaligned(23);
+    pointer.add(2).cast::<u64>().write_unaligned(gdt as u64);
+    asm!(
+        "jmp {page}",
+        page = in(reg) page,
+        in("rdi") entry,
+        in("rsi") info,
+        in("rdx") pointer,
+        options(noreturn)
+    );
+}
+
+/// Jump to the EFI amd64 entry of kernel at `entry`, with boot services running.
+///
+/// # Safety
+///
+/// The kernel and `info` must be loaded.
+pub unsafe fn enter_amd64(entry: u64, info: u64) -> ! {
+    asm!(
+        "mov rbx, {info}",
+        "jmp {entry}",
+        info = in(reg) info,
+        entry = in(reg) entry,
+        in("eax") BOOTLOADER_MAGIC,
+        options(noreturn)
+    );
+}
+
+/// Stop the CPU, for errors after boot services are exited, when nothing can be reported.
+pub fn halt() -> ! {
+    loop {
+        unsafe { asm!("cli", "hlt", options(nomem, nostack)) };
+    }
+}
@@ -1,3 +0,0 @@
-//! Config file
-//! 
-//! This file is not part of copyrighted source code.
@@ -2,11 +2,15 @@
 //!
 //! This can be used to compose boot options with an user-friendly interface.
 
-use alloc::{string::String, vec, vec::Vec};
+use alloc::{borrow::ToOwned, format, string::String, vec, vec::Vec};
 use anyhow::{anyhow, Result};
 use config::bootconf::{BootOption, BootOptionItem, BootOptionKind, BootOptionSelection};
+use config::{BootItem, BootTarget};
 use serde::{Deserialize, Serialize};
 
+#[cfg(target_os = "uefi")]
+use crate::platform::efi::var::{get_var, set_var};
+
 extern crate alloc;
 
 pub fn make_parameter(
@@ -16,7 +20,17 @@ pub fn make_parameter(
     if options.len() != selections.len() {
         return Err(anyhow!("option and selection length mismatch."));
     }
-    let cmdline_len = options.iter().map(|x| x.pos).max().unwrap() as usize + 1;
+    if let Some(option) = options.iter().find(|x| x.pos < 0) {
+        return Err(anyhow!(
+            "option {} has negative cmdline position {}.",
+            option.identifier,
+            option.pos
+        ));
+    }
+    let Some(max_pos) = options.iter().map(|x| x.pos).max() else {
+        return Ok(vec![]);
+    };
+    let cmdline_len = max_pos as usize + 1;
     let mut ret = vec![String::new(); cmdline_len];
 
     for (i, j) in options.iter().zip(selections) {
@@ -44,9 +58,113 @@ pub fn make_parameter(
                     target.push(' ');
                 }
             }
+            // Unknown options are ignored.
+            (BootOptionKind::Default, _) => {}
             _ => return Err(anyhow!("option type and selection type mismatch.")),
         }
     }
 
     Ok(ret)
 }
+
+/// Append parameters made by `make_parameter` to cmdlines of the target.
+///
+/// Position 0 is the cmdline of the target itself, and `n` that of its `n`th multiboot2 module.
+pub fn apply_parameter(target: &mut BootTarget, parameters: &[String]) -> Result<()> {
+    for (pos, parameter) in parameters.iter().enumerate() {
+        let parameter = parameter.trim_end();
+        if parameter.is_empty() {
+            continue;
+        }
+        if !target.append_cmdline(pos, parameter) {
+            return Err(anyhow!("target has no cmdline at position {}.", pos));
+        }
+    }
+    Ok(())
+}
+
+/// Encode selections for storage, one selection per line.
+///
+/// * `b0`/`b1`: Bool
+/// * `m<index>`: Multiple
+/// * `t<value>`: Template with value, `n` for no value.
+///
+/// A trivial format instead of JSON keeps the variable small and readable with `dmpstore`.
+pub fn encode_selections(selections: &[BootOptionSelection]) -> String {
+    let mut ret = String::new();
+    for i in selections {
+        let line = match i {
+            BootOptionSelection::Bool(b) => format!("b{}", *b as u8),
+            BootOptionSelection::Multiple(which) => format!("m{}", which),
+            BootOptionSelection::Template(Some(value)) => format!("t{}", value.replace('\n', " ")),
+            BootOptionSelection::Template(None) | BootOptionSelection::Default => "n".to_owned(),
+        };
+        ret.push_str(&line);
+        ret.push('\n');
+    }
+    ret
+}
+
+/// Decode selections made by `encode_selections`.
+pub fn decode_selections(s: &str) -> Result<Vec<BootOptionSelection>> {
+    s.lines()
+        .map(|line| {
+            let (kind, value) = line.split_at(line.len().min(1));
+            match kind {
+                "b" => Ok(BootOptionSelection::Bool(value == "1")),
+                "m" => value
+                    .parse()
+                    .map(BootOptionSelection::Multiple)
+                    .map_err(|_| anyhow!("bad selection index {}.", value)),
+                "t" => Ok(BootOptionSelection::Template(Some(value.to_owned()))),
+                "n" => Ok(BootOptionSelection::Template(None)),
+                _ => Err(anyhow!("unknown selection {}.", line)),
+            }
+        })
+        .collect()
+}
+
+/// Check if saved selections still fit options, as config may change in between.
+pub fn selections_match(options: &[BootOption], selections: &[BootOptionSelection]) -> bool {
+    options.len() == selections.len()
+        && options
+            .iter()
+            .zip(selections)
+            .all(|(i, j)| match (&i.option, j) {
+                (BootOptionKind::Bool(_), BootOptionSelection::Bool(_)) => true,
+                (BootOptionKind::Multiple(k), BootOptionSelection::Multiple(which)) => {
+                    *which >= 0 && (*which as usize) < k.len()
+                }
+                (BootOptionKind::Template(_), BootOptionSelection::Template(_)) => true,
+                (BootOptionKind::Default, _) => true,
+                _ => false,
+            })
+}
+
+/// Variable of selections, keyed by target, as items in different menus may share a name.
+#[cfg(target_os = "uefi")]
+fn selections_var(item: &BootItem) -> String {
+    format!("Options-{:016x}", item.target.fingerprint())
+}
+
+/// Load last selections of the item, which are used as defaults.
+///
+/// Selections which no longer fit current options are dropped.
+#[cfg(target_os = "uefi")]
+pub fn load_selections(
+    item: &BootItem,
+    options: &[BootOption],
+) -> Option<Vec<BootOptionSelection>> {
+    let data = get_var(&selections_var(item)).ok()?;
+    let selections = decode_selections(core::str::from_utf8(&data).ok()?).ok()?;
+    selections_match(options, &selections).then_some(selections)
+}
+
+/// Save selections of the item for next boot.
+#[cfg(target_os = "uefi")]
+pub fn save_selections(item: &BootItem, selections: &[BootOptionSelection]) -> Result<()> {
+    set_var(
+        &selections_var(item),
+        encode_selections(selections).as_bytes(),
+    )
+}
@@ -0,0 +1,131 @@
+//! Text form of boot targets, for editing a whole entry in a text area.
+//!
+//! Each line is a `key: value` pair, and `initrd` and `module` may appear more than once:
+//!
+//! ```text
+//! kernel: /vmlinuz
+//! initrd: /initrd.img
+//! devicetree: /dtbs/board.dtb
+//! cmdline: root=/dev/sda1
+//! ```
+//!
+//! A multiboot2 module is its path followed by its cmdline, such as `module: /vmlinuz quiet`.
+//!
+//! A message is edited as is.
+
+use alloc::{borrow::ToOwned, format, string::String, vec::Vec};
+use anyhow::{anyhow, Result};
+use config::{BootTarget, ImageLocation, Module};
+
+fn image_path(image: &ImageLocation) -> Option<&str> {
+    match image {
+        ImageLocation::Path { path } => Some(path),
+        _ => None,
+    }
+}
+
+/// Make text form of the target.
+///
+/// Returns `None` if the target can not be edited as text.
+pub fn to_text(target: &BootTarget) -> Option<String> {
+    match target {
+        BootTarget::EFI { path, cmdline } => Some(format!(
+            "path: {}\ncmdline: {}",
+            path,
+            cmdline.as_deref().unwrap_or("")
+        )),
+        BootTarget::Linux {
+            kernel,
+            initrd,
+            cmdline,
+            devicetree,
+        } => {
+            let mut ret = format!("kernel: {}\n", image_path(kernel)?);
+            for i in initrd {
+                ret.push_str(&format!("initrd: {}\n", image_path(i)?));
+            }
+            if let Some(devicetree) = devicetree {
+                ret.push_str(&format!("devicetree: {}\n", image_path(devicetree)?));
+            }
+            ret.push_str(&format!("cmdline: {}", cmdline));
+            Some(ret)
+        }
+        BootTarget::Multiboot2 {
+            kernel,
+            cmdline,
+            modules,
+        } => {
+            let mut ret = format!("kernel: {}\n", image_path(kernel)?);
+            for i in modules {
+                let path = image_path(&i.image)?;
+                match i.cmdline.as_str() {
+                    "" => ret.push_str(&format!("module: {}\n", path)),
+                    cmdline => ret.push_str(&format!("module: {} {}\n", path, cmdline)),
+                }
+            }
+            ret.push_str(&format!("cmdline: {}", cmdline));
+            Some(ret)
+        }
+        BootTarget::Message(msg) => Some(msg.clone()),
+        _ => None,
+    }
+}
+
+/// Parse text made by `to_text`, which was edited from `target`.
+pub fn from_text(target: &BootTarget, text: &str) -> Result<BootTarget> {
+    if let BootTarget::Message(_) = target {
+        return Ok(BootTarget::Message(text.to_owned()));
+    }
+
+    let mut path = None;
+    let mut kernel = None;
+    let mut initrd = Vec::new();
+    let mut modules = Vec::new();
+    let mut devicetree = None;
+    let mut cmdline = String::new();
+    for (n, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (key, value) = line
+            .split_once(':')
+            .ok_or_else(|| anyhow!("line {}: expected `key: value`.", n + 1))?;
+        let value = value.trim().to_owned();
+        match key.trim() {
+            "path" => path = Some(value),
+            "kernel" => kernel = Some(ImageLocation::Path { path: value }),
+            "initrd" => initrd.push(ImageLocation::Path { path: value }),
+            "devicetree" => devicetree = Some(ImageLocation::Path { path: value }),
+            "module" => {
+                let (path, cmdline) = value.split_once(' ').unwrap_or((&value, ""));
+                modules.push(Module {
+                    image: ImageLocation::Path {
+                        path: path.to_owned(),
+                    },
+                    cmdline: cmdline.trim_start().to_owned(),
+                });
+            }
+            "cmdline" => cmdline = value,
+            k => return Err(anyhow!("line {}: unknown key `{}`.", n + 1, k)),
+        }
+    }
+
+    match target {
+        BootTarget::EFI { .. } => Ok(BootTarget::EFI {
+            path: path.ok_or_else(|| anyhow!("`path` is required."))?,
+            cmdline: (!cmdline.is_empty()).then_some(cmdline),
+        }),
+        BootTarget::Linux { .. } => Ok(BootTarget::Linux {
+            kernel: kernel.ok_or_else(|| anyhow!("`kernel` is required."))?,
+            initrd,
+            cmdline,
+            devicetree,
+        }),
+        BootTarget::Multiboot2 { .. } => Ok(BootTarget::Multiboot2 {
+            kernel: kernel.ok_or_else(|| anyhow!("`kernel` is required."))?,
+            cmdline,
+            modules,
+        }),
+        _ => Err(anyhow!("this target can not be edited.")),
+    }
+}
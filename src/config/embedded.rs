@@ -0,0 +1,23 @@
+//! Config compiled into b2 by `embedded-config` feature.
+//!
+//! The config is checked by build script, and is selected by `B2_EMBEDDED_POLICY` at build time.
+
+use anyhow::{Context, Result};
+
+use super::BootConfig;
+
+/// How embedded config is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmbeddedPolicy {
+    /// Used only if no other config is found.
+    Fallback,
+    /// Always used, and other configs are ignored.
+    Override,
+}
+
+include!(concat!(env!("OUT_DIR"), "/embedded_config.rs"));
+
+/// Parse embedded config.
+pub fn embedded_config() -> Result<BootConfig> {
+    BootConfig::parse(EMBEDDED_CONFIG).context("embedded config")
+}
@@ -11,6 +11,7 @@ use anyhow::anyhow;
 use config::boot::Config;
 use config::BootItem;
 use config::BootTarget;
+use config::SCHEMA_VERSION;
 use log::info;
 use serde::Deserialize;
 use serde::Serialize;
@@ -27,53 +28,56 @@ use crate::platform::efi::efi_error::ToError;
 use crate::platform::println;
 
 pub(crate) mod boot_config;
+pub(crate) mod edit;
+#[cfg(feature = "embedded-config")]
+pub(crate) mod embedded;
 pub(crate) mod lock;
+#[cfg(feature = "signed-config")]
+pub(crate) mod signature;
+pub(crate) mod source;
+#[cfg(target_os = "uefi")]
+pub(crate) mod variables;
 
 pub struct BootConfig(pub Config);
 
 impl BootConfig {
+    /// Parse config in JSON.
+    pub fn parse(data: &[u8]) -> anyhow::Result<Self> {
+        config::parse_config(data)
+            .map(BootConfig)
+            .map_err(|e| anyhow!("{}", e))
+    }
+
     pub fn fallback_menu() -> Self {
         BootConfig(Config {
+            version: SCHEMA_VERSION,
             name: Some("b2 Menu".to_owned()),
             message: Some("".to_owned()),
             items: vec![
-                #[cfg(target_os = "uefi")]
-                BootItem {
-                    name: "Linux".to_owned(),
-                    target: BootTarget::EFI {
+                // With signed config, this menu replaces configs which are not trusted, so it has
+                // nothing which can be edited into booting another image.
+                #[cfg(all(target_os = "uefi", not(feature = "signed-config")))]
+                BootItem::new(
+                    "Linux",
+                    BootTarget::EFI {
                         path: "/linux/vmlinuz".to_owned(),
                         cmdline: Some("initrd=\\linux\\initrd.gz".to_owned()),
                     },
-                },
-                BootItem {
-                    name: "Reboot".to_owned(),
-                    target: BootTarget::Reboot,
-                },
-                BootItem {
-                    name: "Poweroff".to_owned(),
-                    target: BootTarget::Poweroff,
-                },
-                BootItem {
-                    name: "Firmware Setup".to_owned(),
-                    target: BootTarget::FirmwareSetup,
-                },
-                BootItem {
-                    name: "Exit".to_owned(),
-                    target: BootTarget::Exit,
-                },
-                BootItem {
-                    name: "Debug Info".to_owned(),
-                    target: BootTarget::Debug,
-                },
+                ),
+                BootItem::new("Reboot", BootTarget::Reboot),
+                BootItem::new("Poweroff", BootTarget::Poweroff),
+                BootItem::new("Firmware Setup", BootTarget::FirmwareSetup),
+                BootItem::new("Exit", BootTarget::Exit),
+                BootItem::new("Debug Info", BootTarget::Debug),
                 #[cfg(debug_assertions)]
-                BootItem {
-                    name: "Panic".to_owned(),
-                    target: BootTarget::Panic,
-                },
+                BootItem::new("Panic", BootTarget::Panic),
             ],
-            default: 0,
+            default: 0.into(),
             timeout: None,
             password: None,
+            drivers: None,
+            variables: None,
+            profiles: None,
         })
     }
 }
@@ -0,0 +1,37 @@
+//! Signature check of configs, by `signed-config` feature.
+//!
+//! Public keys and the policy for unsigned configs are compiled in by build script.
+
+use anyhow::{anyhow, Result};
+use config::signature::{decode_hex, SIGNATURE_LENGTH};
+use ed25519_dalek::{Signature, VerifyingKey};
+
+/// What to do with a config without valid signature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignaturePolicy {
+    /// Skip the config, as if it were broken.
+    Refuse,
+    /// Use embedded config instead.
+    Embedded,
+    /// Use the config, with a warning.
+    Warn,
+}
+
+include!(concat!(env!("OUT_DIR"), "/config_keys.rs"));
+
+/// Check `signature`, in hex, of `data` against compiled-in keys.
+pub fn verify(data: &[u8], signature: &[u8]) -> Result<()> {
+    let signature = core::str::from_utf8(signature)
+        .ok()
+        .and_then(decode_hex)
+        .and_then(|x| <[u8; SIGNATURE_LENGTH]>::try_from(x).ok())
+        .ok_or_else(|| anyhow!("signature is not {} bytes in hex.", SIGNATURE_LENGTH))?;
+    let signature = Signature::from_bytes(&signature);
+    let valid = CONFIG_KEYS.iter().any(|key| {
+        VerifyingKey::from_bytes(key).is_ok_and(|key| key.verify_strict(data, &signature).is_ok())
+    });
+    if !valid {
+        return Err(anyhow!("signature does not match any trusted key."));
+    }
+    Ok(())
+}
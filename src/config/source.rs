@@ -0,0 +1,320 @@
+//! Loading config from its sources.
+//!
+//! Sources and the report of them are in `config::source`, so they are tested on host.
+
+#[cfg(all(target_os = "uefi", feature = "embedded-config"))]
+use super::embedded::{EmbeddedPolicy, EMBEDDED_CONFIG, EMBEDDED_POLICY};
+use alloc::borrow::ToOwned;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use anyhow::{anyhow, Result};
+use config::source::{ConfigSource, LoadReport, CONFIG_FILE, CONFIG_VOLUME_LABEL, FRAGMENT_DIR};
+#[cfg(all(target_os = "uefi", feature = "signed-config"))]
+use {
+    super::signature::{self, SignaturePolicy, SIGNATURE_POLICY},
+    anyhow::Context,
+    config::signature::{SIGNATURE_SUFFIX, SIGNATURE_VARIABLE},
+};
+#[cfg(target_os = "uefi")]
+use {
+    super::BootConfig,
+    crate::platform::efi::block::{get_protocol, path_text},
+    crate::platform::efi::fs::{mount_path, EfiFileSystem, EfiVolumes},
+    crate::platform::efi::var::get_var,
+    alloc::vec,
+    config::Fragment,
+    io::fs::{path, FileSystem, VolumeAddress, Volumes},
+    log::{info, warn},
+    uefi::proto::loaded_image::LoadedImage,
+    uefi_services::system_table,
+};
+
+/// Sources to try, from `ConfigOrder` variable if set.
+///
+/// An overriding embedded config is the only source, so it cannot be bypassed by setting the variable.
+#[cfg(target_os = "uefi")]
+fn source_order() -> Vec<ConfigSource> {
+    #[cfg(feature = "embedded-config")]
+    if EMBEDDED_POLICY == EmbeddedPolicy::Override {
+        return vec![ConfigSource::Embedded];
+    }
+    // Embedded config is left out of default order if not built in, to keep report short.
+    let default = || {
+        ConfigSource::DEFAULT_ORDER
+            .iter()
+            .copied()
+            .filter(|x| cfg!(feature = "embedded-config") || *x != ConfigSource::Embedded)
+            .collect()
+    };
+    let Ok(order) = get_var("ConfigOrder") else {
+        return default();
+    };
+    core::str::from_utf8(&order)
+        .map_err(|_| "not in UTF-8.".to_owned())
+        .and_then(ConfigSource::parse_order)
+        .unwrap_or_else(|e| {
+            warn!("ignoring ConfigOrder variable: {}", e);
+            default()
+        })
+}
+
+/// Directory of b2 image on its volume.
+#[cfg(target_os = "uefi")]
+fn image_dir() -> Result<String> {
+    let st = system_table();
+    let bs = st.boot_services();
+    let image = get_protocol::<LoadedImage>(bs, bs.image_handle())?;
+    let file = image
+        .file_path()
+        .ok_or_else(|| anyhow!("b2 is not loaded from a file."))?;
+    Ok(path::parent(&path_text(file)?))
+}
+
+/// Locations of a source, each with its content or why it cannot be read.
+#[cfg(target_os = "uefi")]
+fn read_source(source: ConfigSource) -> Vec<(String, Result<Vec<u8>>)> {
+    let root_file = path::join("\\", CONFIG_FILE);
+    match source {
+        #[cfg(feature = "embedded-config")]
+        ConfigSource::Embedded => {
+            vec![("embedded config".to_owned(), Ok(EMBEDDED_CONFIG.to_vec()))]
+        }
+        #[cfg(not(feature = "embedded-config"))]
+        ConfigSource::Embedded => vec![(
+            "embedded config".to_owned(),
+            Err(anyhow!("b2 is built without embedded-config feature.")),
+        )],
+        ConfigSource::File => {
+            let mut files = Vec::new();
+            match image_dir() {
+                Ok(dir) => files.push(path::join(&dir, CONFIG_FILE)),
+                Err(e) => warn!("failed to find directory of b2: {}", e),
+            }
+            if !files.contains(&root_file) {
+                files.push(root_file);
+            }
+            files
+                .into_iter()
+                .map(|x| {
+                    let data = EfiFileSystem::current().and_then(|mut fs| fs.read(&x));
+                    (x, data)
+                })
+                .collect()
+        }
+        ConfigSource::Variable => vec![("Config variable".to_owned(), get_var("Config"))],
+        ConfigSource::Label => {
+            let location = format!("LABEL={}:{}", CONFIG_VOLUME_LABEL, root_file);
+            let address = VolumeAddress::Label(CONFIG_VOLUME_LABEL.to_owned());
+            let data = EfiVolumes::new()
+                .and_then(|mut x| x.mount_by(&address))
+                .and_then(|mut fs| fs.read(&root_file));
+            vec![(location, data)]
+        }
+    }
+}
+
+/// Read signature of a config, which is next to the config file, or in its own variable.
+#[cfg(all(target_os = "uefi", feature = "signed-config"))]
+fn read_signature(source: ConfigSource, location: &str) -> Result<Vec<u8>> {
+    match source {
+        ConfigSource::Variable => get_var(SIGNATURE_VARIABLE),
+        // Locations of files are paths, with volume for `label`.
+        _ => {
+            let (mut fs, path) = mount_path(&format!("{}{}", location, SIGNATURE_SUFFIX))?;
+            fs.read(&path)
+        }
+    }
+}
+
+/// Check signature of a config. Embedded config is trusted.
+#[cfg(all(target_os = "uefi", feature = "signed-config"))]
+fn check_signature(source: ConfigSource, location: &str, data: &[u8]) -> Result<()> {
+    if source == ConfigSource::Embedded {
+        return Ok(());
+    }
+    let signature = read_signature(source, location).context("no signature")?;
+    signature::verify(data, &signature)
+}
+
+/// Parse config from a location, and record the result.
+#[cfg(target_os = "uefi")]
+fn try_parse(
+    source: ConfigSource,
+    location: String,
+    data: Result<Vec<u8>>,
+    report: &mut LoadReport,
+) -> Option<BootConfig> {
+    let parsed = data.and_then(|x| {
+        config::parse_config(&x).map_err(|e| {
+            let reason = anyhow!("{}", e.summary());
+            report.parse_errors.push((location.clone(), e));
+            reason
+        })
+    });
+    match parsed {
+        Ok(config) => {
+            let config = BootConfig(config);
+            info!("using config {} ({})", location, source.name());
+            for i in config.0.compatibility_warnings() {
+                warn!("config {}: {}", location, i);
+                report.warnings.push(i);
+            }
+            report.used = Some((source, location));
+            Some(config)
+        }
+        Err(e) => {
+            let reason = format!("{:#}", e);
+            warn!(
+                "skipped config {} ({}): {}",
+                location,
+                source.name(),
+                reason
+            );
+            report.reject(source, &location, &reason);
+            None
+        }
+    }
+}
+
+/// Directory of fragments of config from `location`, in form of locations of files.
+///
+/// Fragments of config files are next to them, and those of other configs are next to b2 image.
+#[cfg(target_os = "uefi")]
+fn fragment_dir(source: ConfigSource, location: &str) -> Result<String> {
+    match source {
+        // Locations of files end with `CONFIG_FILE`, whose fragments are in `FRAGMENT_DIR`.
+        ConfigSource::File | ConfigSource::Label => Ok(format!("{}.d", location)),
+        ConfigSource::Variable | ConfigSource::Embedded => {
+            Ok(path::join(&image_dir()?, FRAGMENT_DIR))
+        }
+    }
+}
+
+/// Read and parse a fragment, checking its signature as a config file.
+#[cfg(target_os = "uefi")]
+fn read_fragment(
+    fs: &mut EfiFileSystem,
+    path: &str,
+    location: &str,
+    report: &mut LoadReport,
+) -> Result<Fragment> {
+    let data = fs.read(path)?;
+    #[cfg(feature = "signed-config")]
+    if let Err(e) = check_signature(ConfigSource::File, location, &data) {
+        if SIGNATURE_POLICY != SignaturePolicy::Warn {
+            return Err(e.context("refused"));
+        }
+        let warning = format!("{}: {:#}, used anyway", location, e);
+        warn!("config {}", warning);
+        report.warnings.push(warning);
+    }
+    config::parse::parse::<Fragment>(&data).map_err(|e| {
+        let reason = anyhow!("{}", e.summary());
+        report.parse_errors.push((location.to_owned(), e));
+        reason
+    })
+}
+
+/// Merge fragments of config from `location` in order of their names.
+///
+/// Broken fragments are skipped, and the rest are still merged.
+#[cfg(target_os = "uefi")]
+fn merge_fragments(
+    source: ConfigSource,
+    location: &str,
+    config: &mut BootConfig,
+    report: &mut LoadReport,
+) {
+    #[cfg(feature = "embedded-config")]
+    if EMBEDDED_POLICY == EmbeddedPolicy::Override {
+        return;
+    }
+    let dir = match fragment_dir(source, location) {
+        Ok(dir) => dir,
+        Err(e) => {
+            warn!("failed to find fragments of config {}: {}", location, e);
+            return;
+        }
+    };
+    // No fragment is there, which is usual.
+    let Ok((mut fs, dir_path)) = mount_path(&dir) else {
+        return;
+    };
+    let Ok(entries) = fs.read_dir(&dir_path) else {
+        return;
+    };
+    let mut names: Vec<String> = entries
+        .into_iter()
+        .filter(|x| !x.is_dir && x.name.to_ascii_lowercase().ends_with(".json"))
+        .map(|x| x.name)
+        .collect();
+    names.sort();
+    for name in names {
+        let location = format!("{}\\{}", dir, name);
+        match read_fragment(&mut fs, &path::join(&dir_path, &name), &location, report) {
+            Ok(fragment) => {
+                info!("merging config fragment {}", location);
+                for i in fragment.compatibility_warnings() {
+                    warn!("config {}: {}", location, i);
+                    report.warnings.push(format!("{}: {}", location, i));
+                }
+                config.0.merge(fragment);
+                report.fragments.push(location);
+            }
+            Err(e) => {
+                let reason = format!("{:#}", e);
+                warn!("skipped config fragment {}: {}", location, reason);
+                report.reject(source, &location, &reason);
+            }
+        }
+    }
+}
+
+/// Load config from the first source with a valid one.
+///
+/// Returns `None` if no source has a valid config. Sources tried are logged and reported.
+#[cfg(target_os = "uefi")]
+pub fn load_config() -> (Option<BootConfig>, LoadReport) {
+    let mut report = LoadReport::default();
+    for source in source_order() {
+        for (location, data) in read_source(source) {
+            #[cfg(feature = "signed-config")]
+            let data = match data {
+                Ok(data) => match check_signature(source, &location, &data) {
+                    Ok(()) => Ok(data),
+                    Err(e) if SIGNATURE_POLICY == SignaturePolicy::Warn => {
+                        let warning = format!("{}: {:#}, used anyway", location, e);
+                        warn!("config {}", warning);
+                        report.warnings.push(warning);
+                        Ok(data)
+                    }
+                    Err(e) if SIGNATURE_POLICY == SignaturePolicy::Embedded => {
+                        let reason = format!("{:#}, using embedded config", e);
+                        warn!(
+                            "skipped config {} ({}): {}",
+                            location,
+                            source.name(),
+                            reason
+                        );
+                        report.reject(source, &location, &reason);
+                        let (location, data) = read_source(ConfigSource::Embedded).remove(0);
+                        let mut config =
+                            try_parse(ConfigSource::Embedded, location.clone(), data, &mut report);
+                        if let Some(config) = &mut config {
+                            merge_fragments(ConfigSource::Embedded, &location, config, &mut report);
+                        }
+                        return (config, report);
+                    }
+                    Err(e) => Err(e.context("refused")),
+                },
+                Err(e) => Err(e),
+            };
+            if let Some(mut config) = try_parse(source, location.clone(), data, &mut report) {
+                merge_fragments(source, &location, &mut config, &mut report);
+                return (Some(config), report);
+            }
+        }
+    }
+    (None, report)
+}
@@ -0,0 +1,57 @@
+//! Built-in variables of config, resolved when an entry is booted.
+
+use alloc::borrow::{Cow, ToOwned};
+use alloc::format;
+use alloc::string::String;
+use anyhow::{anyhow, Result};
+use config::{BootItem, Config};
+use log::warn;
+
+use crate::platform::efi::fs::{current_device, partition_guid};
+use crate::platform::efi::machine::system_info;
+use crate::platform::efi::var::{get_var, set_var};
+
+/// b2 variable counting entries booted, in decimal.
+pub const BOOT_COUNT_VARIABLE: &str = "BootCount";
+
+/// Count a boot in `BOOT_COUNT_VARIABLE`, and return the count.
+fn count_boot() -> u64 {
+    let count = get_var(BOOT_COUNT_VARIABLE)
+        .ok()
+        .and_then(|x| core::str::from_utf8(&x).ok()?.trim().parse::<u64>().ok())
+        .unwrap_or(0)
+        .saturating_add(1);
+    if let Err(e) = set_var(BOOT_COUNT_VARIABLE, format!("{}", count).as_bytes()) {
+        warn!("failed to save boot count: {}", e);
+    }
+    count
+}
+
+/// Value of a built-in variable, when booting `item`.
+fn builtin(name: &str, item: &BootItem, boot_count: u64) -> Option<String> {
+    match name {
+        "b2.esp_partuuid" => partition_guid(current_device().ok()?),
+        "b2.entry" => Some(item.name.clone()),
+        "b2.boot_count" => Some(format!("{}", boot_count)),
+        "b2.smbios_serial" => system_info().map(|x| x.serial),
+        _ => None,
+    }
+}
+
+/// Substitute variables in cmdline and paths of `item`, to boot it.
+///
+/// Boots of entries taking a cmdline or path are counted, whether they use the count or not.
+pub fn expand_item<'a>(config: &Config, item: Cow<'a, BootItem>) -> Result<Cow<'a, BootItem>> {
+    let mut target = item.target.clone();
+    if target.strings_mut().is_empty() {
+        return Ok(item);
+    }
+    let boot_count = count_boot();
+    let mut resolve = |name: &str| builtin(name, &item, boot_count);
+    target = config
+        .expand_target(&target, &mut resolve)
+        .map_err(|e| anyhow!("{}.", e))?;
+    let mut item = item.into_owned();
+    item.target = target;
+    Ok(Cow::Owned(item))
+}
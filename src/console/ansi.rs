@@ -2,9 +2,9 @@
 //!
 //! This may be useful for serial consoles.
 extern crate alloc;
-use super::console::ConsoleStyle;
 use alloc::string::String;
 use anyhow::Result;
+use io::console::ConsoleStyle;
 
 /// trait for control sequence-based terminal
 ///
@@ -1,5 +1,6 @@
-use crate::io::console::CursorStyle;
 use crate::platform::ToError;
+use alloc::borrow::Cow;
+use alloc::borrow::ToOwned;
 use alloc::format;
 use alloc::string::String;
 use anyhow::anyhow;
@@ -10,34 +11,141 @@ use argon2::PasswordVerifier;
 use config::BootOption;
 use config::BootOptionKind;
 use config::BootOptionSelection;
+use io::console::{AcceleratorKey, CursorStyle, Key};
 
-use crate::io::console::Console;
-use crate::io::{LineEdit, ReadString};
+use crate::config::edit;
 use config::BootItem;
+use config::BootTarget;
 use config::Config;
+use config::Profile;
+use io::complete::CmdlineCompleter;
+use io::console::Console;
+use io::{EditConfig, LineEdit};
 
 use alloc::{vec, vec::Vec};
 
+/// A menu shown, which is the top level menu or a submenu.
+struct Level<'a> {
+    title: &'a str,
+    message: Option<&'a str>,
+    items: &'a [BootItem],
+}
+
+impl<'a> Level<'a> {
+    fn top(config: &'a Config) -> Self {
+        Self {
+            title: config.name.as_deref().unwrap_or("b2 loader"),
+            message: config.message.as_deref(),
+            items: &config.items,
+        }
+    }
+
+    /// Level of `item`, if it is a submenu.
+    fn submenu(item: &'a BootItem) -> Option<Self> {
+        match &item.target {
+            BootTarget::Submenu { name, items } => Some(Self {
+                title: name.as_deref().unwrap_or(&item.name),
+                message: None,
+                items,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Profiles of config, which apply to all items taking a cmdline.
+fn profiles(config: &Config) -> &[Profile] {
+    config.profiles.as_deref().unwrap_or_default()
+}
+
+/// Whether an item in `items` or their submenus has a hotkey.
+fn has_hotkey(items: &[BootItem]) -> bool {
+    items.iter().any(|x| {
+        x.usable_hotkey().is_some()
+            || matches!(&x.target, BootTarget::Submenu { items, .. } if has_hotkey(items))
+    })
+}
+
+/// What is typed at `Boot:` prompt.
+enum Input<'a> {
+    /// Hotkey of an item, with its path.
+    Hotkey(Vec<u32>, &'a BootItem),
+    /// `Esc`, to leave a submenu.
+    Back,
+    /// A line, which is left in buffer.
+    Line,
+}
+
 /// Basic Menu - Text based menu.
 ///
-pub struct BasicMenu {}
+pub struct BasicMenu {
+    /// Whether the user has passed password check in this session.
+    authenticated: bool,
+}
 
 impl BasicMenu {
+    pub fn new() -> Self {
+        Self {
+            authenticated: false,
+        }
+    }
+
     /// Show menu on basic console.
     ///
-    pub fn render(config: &Config, console: &mut dyn Console) -> Result<()> {
+    /// `nested` is true for submenus, which are left with `Esc`.
+    fn render(
+        level: &Level,
+        profiles: &[Profile],
+        nested: bool,
+        console: &mut dyn Console,
+    ) -> Result<()> {
         // Show title
-        let title = config.name.as_deref().unwrap_or("b2 loader");
-        writeln!(console, "{}", title).map_err(|_| anyhow!("failed to write"))?;
+        writeln!(console, "{}", level.title).map_err(|_| anyhow!("failed to write"))?;
         // Show message
-        if let Some(msg) = config.message.as_ref() {
+        if let Some(msg) = level.message {
             writeln!(console, "{}", msg).map_err(|_| anyhow!("failed to write"))?;
         }
         // Show items.
-        for (i, j) in config.items.iter().enumerate() {
-            writeln!(console, "[{}]: {}", i + 1, j.name).map_err(|_| anyhow!("failed to write"))?;
+        for (i, j) in level.items.iter().enumerate() {
+            let more = if Level::submenu(j).is_some() {
+                " >"
+            } else {
+                ""
+            };
+            match j.usable_hotkey() {
+                Some(hotkey) => writeln!(console, "[{}]: {}{} ({})", i + 1, j.name, more, hotkey),
+                None => writeln!(console, "[{}]: {}{}", i + 1, j.name, more),
+            }
+            .map_err(|_| anyhow!("failed to write"))?;
         }
         // Show prompt.
+        writeln!(
+            console,
+            "Enter a number to boot, e<number> to edit its cmdline, or E<number> to edit the entry."
+        )
+        .map_err(|_| anyhow!("failed to write"))?;
+        // Profiles are listed once, as they apply to all items taking them.
+        let profiled = match profiles.is_empty() {
+            true => None,
+            false => level.items.iter().position(BootItem::takes_profiles),
+        };
+        if let Some(k) = profiled {
+            let names: Vec<String> = profiles
+                .iter()
+                .enumerate()
+                .map(|(n, x)| format!(".{} {}", n + 1, x.name))
+                .collect();
+            writeln!(
+                console,
+                "Profiles: {}, after a number such as {}.1.",
+                names.join(", "),
+                k + 1
+            )
+            .map_err(|_| anyhow!("failed to write"))?;
+        }
+        if nested {
+            writeln!(console, "Press Esc to go back.").map_err(|_| anyhow!("failed to write"))?;
+        }
 
         Ok(())
     }
@@ -49,7 +157,7 @@ impl BasicMenu {
             let hash = PasswordHash::new(&phc).map_err(|e| anyhow!("password: {}", e))?;
             loop {
                 let mut passwd = String::new();
-                console.edit_line(&mut passwd, "Password: ");
+                console.edit_secret(&mut passwd, "Password: ")?;
                 match argon2.verify_password(passwd.as_bytes(), &hash) {
                     Ok(_) => {
                         break;
@@ -63,33 +171,266 @@ impl BasicMenu {
 
         Ok(())
     }
+    /// Check if the user may change boot parameters, asking for password if required.
+    ///
+    /// Without a password configured, everyone is allowed.
+    fn authorize(&mut self, config: &Config, console: &mut dyn Console) -> Result<bool> {
+        if config.password.is_none() || self.authenticated {
+            return Ok(true);
+        }
+        #[cfg(feature = "password")]
+        {
+            Self::verify(config, console)?;
+            self.authenticated = true;
+        }
+        Ok(self.authenticated)
+    }
+
+    /// Edit cmdline of an item for this boot only.
+    ///
+    /// Returns `None` if the item can not be edited.
+    fn edit_cmdline(
+        &mut self,
+        config: &Config,
+        item: &BootItem,
+        console: &mut dyn Console,
+    ) -> Result<Option<BootItem>> {
+        if !self.authorize(config, console)? {
+            writeln!(console, "Editing is locked by password.").core_err()?;
+            return Ok(None);
+        }
+        let Some(cmdline) = item.target.cmdline() else {
+            writeln!(console, "\"{}\" does not take a cmdline.", item.name).core_err()?;
+            return Ok(None);
+        };
+        let mut buf = cmdline.to_owned();
+        let options = item.options.as_deref().unwrap_or_default();
+        let mut completer = CmdlineCompleter::new(options, crate::platform::list_dir);
+        console.edit_line_complete(&mut buf, "cmdline: ", &mut completer)?;
+        // Only the copy is changed, so the edit never goes back to config.
+        let mut item = item.clone();
+        item.target.set_cmdline(buf);
+        Ok(Some(item))
+    }
+
+    /// Edit a whole item in text area for this boot only.
+    ///
+    /// Returns `None` if the item can not be edited, or the edit is cancelled.
+    fn edit_entry(
+        &mut self,
+        config: &Config,
+        level: &Level,
+        nested: bool,
+        item: &BootItem,
+        console: &mut dyn Console,
+    ) -> Result<Option<BootItem>> {
+        if !self.authorize(config, console)? {
+            writeln!(console, "Editing is locked by password.").core_err()?;
+            return Ok(None);
+        }
+        let Some(mut buf) = edit::to_text(&item.target) else {
+            writeln!(console, "\"{}\" can not be edited.", item.name).core_err()?;
+            return Ok(None);
+        };
+        let title = format!("Editing \"{}\"", item.name);
+        let saved = console.edit_area(
+            &mut buf,
+            &EditConfig {
+                title: &title,
+                replace: false,
+            },
+        )?;
+        // Text area has taken the screen.
+        Self::render(level, profiles(config), nested, console)?;
+        if !saved {
+            return Ok(None);
+        }
+        match edit::from_text(&item.target, &buf) {
+            Ok(target) => {
+                let mut item = item.clone();
+                item.target = target;
+                Ok(Some(item))
+            }
+            Err(e) => {
+                writeln!(console, "Invalid entry: {}", e).core_err()?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Find the path of item with `key` as hotkey, in all menus.
+    ///
+    /// Items with an invalid or reserved hotkey are ignored.
+    pub fn hotkey_path(config: &Config, key: &Key) -> Option<Vec<u32>> {
+        fn find(items: &[BootItem], key: &Key, path: &mut Vec<u32>) -> bool {
+            for (n, item) in items.iter().enumerate() {
+                path.push(n as u32);
+                let hit = item
+                    .usable_hotkey()
+                    .and_then(Key::from_name)
+                    .is_some_and(|k| &k == key);
+                if hit {
+                    return true;
+                }
+                if let BootTarget::Submenu { items, .. } = &item.target {
+                    if find(items, key, path) {
+                        return true;
+                    }
+                }
+                path.pop();
+            }
+            false
+        }
+        let mut path = Vec::new();
+        find(&config.items, key, &mut path).then_some(path)
+    }
+
+    /// Read a line at `Boot:` prompt.
+    ///
+    /// If an item has a hotkey, or in a submenu, first key is read before editing,
+    /// so the item is returned if it is pressed, or `Esc` leaves the submenu.
+    fn read_selection<'a>(
+        config: &'a Config,
+        nested: bool,
+        buf: &mut String,
+        console: &mut dyn Console,
+    ) -> Result<Input<'a>> {
+        let prompt = "Boot:";
+        if nested || has_hotkey(&config.items) {
+            let line = console.get_cursor()?.1;
+            console.write_str(prompt).core_err()?;
+            let key = console.wait_for_key()?;
+            if let Some(path) = Self::hotkey_path(config, &key) {
+                console.write_char('\n').core_err()?;
+                if let Some(item) = config.item(&path) {
+                    return Ok(Input::Hotkey(path, item));
+                }
+            }
+            match key {
+                Key::Accelerator(AcceleratorKey::Enter) => {
+                    console.write_char('\n').core_err()?;
+                    return Ok(Input::Line);
+                }
+                Key::Accelerator(AcceleratorKey::Esc) if nested => {
+                    console.write_char('\n').core_err()?;
+                    return Ok(Input::Back);
+                }
+                Key::Printable(k) if !k.is_control() => buf.push(k),
+                _ => {}
+            }
+            // Line editor draws the prompt again.
+            console.set_cursor(0, line)?;
+        }
+        console.edit_line(buf, prompt)?;
+        Ok(Input::Line)
+    }
+
     /// Read selected boot option.
     ///
+    /// Menu is opened at the submenu at `open`, if it is the path of one, and `Esc` goes back
+    /// through its parents. An entry is returned as owned if user has edited it.
     pub fn prompt<'a>(
-        &self,
+        &mut self,
         config: &'a Config,
+        open: &[u32],
         console: &mut dyn Console,
-    ) -> Result<&'a BootItem> {
+    ) -> Result<Cow<'a, BootItem>> {
         #[cfg(feature = "password")]
-        Self::verify(config, console)?;
+        {
+            Self::verify(config, console)?;
+            self.authenticated = true;
+        }
 
-        Self::render(config, console)?;
-        let len = config.items.len();
+        let mut levels = vec![Level::top(config)];
+        for n in 1..=open.len() {
+            match config.item(&open[..n]).and_then(Level::submenu) {
+                Some(level) => levels.push(level),
+                None => break,
+            }
+        }
+        let mut redraw = true;
         loop {
+            let level = levels.last().unwrap();
+            let nested = levels.len() > 1;
+            if core::mem::take(&mut redraw) {
+                Self::render(level, profiles(config), nested, console)?;
+            }
             let mut buf = String::new();
-            console.edit_line(&mut buf, "Boot:")?;
+            match Self::read_selection(config, nested, &mut buf, console)? {
+                Input::Hotkey(path, item) => match Level::submenu(item) {
+                    // Parents of a submenu are entered, so `Esc` goes back through them.
+                    Some(_) => {
+                        levels.truncate(1);
+                        levels.extend(
+                            (1..=path.len())
+                                .filter_map(|n| config.item(&path[..n]).and_then(Level::submenu)),
+                        );
+                        redraw = true;
+                        continue;
+                    }
+                    None => return Ok(Cow::Borrowed(item)),
+                },
+                Input::Back => {
+                    levels.pop();
+                    redraw = true;
+                    continue;
+                }
+                Input::Line => {}
+            }
             let buf = buf.trim();
             if buf.is_empty() {
                 continue;
             }
+            // `e` edits cmdline, and `E` edits the whole entry.
+            let (edit, buf) = match buf.chars().next() {
+                Some(c @ ('e' | 'E')) => (Some(c), buf[1..].trim_start()),
+                _ => (None, buf),
+            };
+            // A profile is chosen after the number, such as `2.1`.
+            let (buf, profile) = match buf.split_once('.') {
+                Some((buf, profile)) => (buf, Some(profile)),
+                None => (buf, None),
+            };
             let selection: Result<usize, _> = buf.parse();
             if let Ok(k) = selection {
-                if k < 1 || k > len {
+                if k < 1 || k > level.items.len() {
                     writeln!(console, "Invalid option.")
                         .map_err(|e| anyhow!("failed to write due to {}", e))?;
                     continue;
                 }
-                return Ok(&config.items[k - 1]);
+                let mut item = Cow::Borrowed(&level.items[k - 1]);
+                if let Some(profile) = profile {
+                    let profile = profile
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|x| x.checked_sub(1))
+                        .and_then(|x| profiles(config).get(x));
+                    let Some(profile) = profile else {
+                        writeln!(console, "Invalid profile.").core_err()?;
+                        continue;
+                    };
+                    match item.with_profile(profile) {
+                        Some(k) => item = Cow::Owned(k),
+                        None => {
+                            writeln!(console, "\"{}\" does not take profiles.", item.name)
+                                .core_err()?;
+                            continue;
+                        }
+                    }
+                }
+                let edited = match (edit, Level::submenu(&level.items[k - 1])) {
+                    (None, Some(submenu)) => {
+                        levels.push(submenu);
+                        redraw = true;
+                        continue;
+                    }
+                    (None, None) => return Ok(item),
+                    (Some('e'), _) => self.edit_cmdline(config, &item, console)?,
+                    (Some(_), _) => self.edit_entry(config, level, nested, &item, console)?,
+                };
+                if let Some(item) = edited {
+                    return Ok(Cow::Owned(item));
+                }
             } else {
                 writeln!(console, "Invalid option.")
                     .map_err(|e| anyhow!("failed to write due to {}", e))?;
@@ -99,28 +440,42 @@ impl BasicMenu {
     }
 
     /// Read boot config by user.
+    ///
+    /// `defaults` are preselected, and should match `config`.
     pub fn boot_config<'a>(
         &self,
         config: &'a [BootOption],
+        defaults: Option<&[BootOptionSelection]>,
         console: &mut dyn Console,
-    ) -> Result<()> {
+    ) -> Result<Vec<BootOptionSelection>> {
         let mut buf = String::new();
         let mut ret: Vec<BootOptionSelection> = vec![];
-        for i in config {
+        for (n, i) in config.iter().enumerate() {
+            let default = defaults.and_then(|x| x.get(n));
             loop {
+                buf.clear();
                 match &i.option {
                     BootOptionKind::Bool(k) => {
-                        let prompt = format!("{}? (y/N/?) ", i.name);
+                        let default = matches!(default, Some(BootOptionSelection::Bool(true)));
+                        let prompt = if default {
+                            format!("{}? (Y/n/?) ", i.name)
+                        } else {
+                            format!("{}? (y/N/?) ", i.name)
+                        };
                         console.edit_line(&mut buf, &prompt)?;
-                        match buf.as_str() {
+                        match buf.trim() {
                             "y" | "Y" => {
                                 ret.push(BootOptionSelection::Bool(true));
                                 break;
                             }
-                            "n" | "N" | "" => {
+                            "n" | "N" => {
                                 ret.push(BootOptionSelection::Bool(false));
                                 break;
                             }
+                            "" => {
+                                ret.push(BootOptionSelection::Bool(default));
+                                break;
+                            }
                             "?" => {
                                 writeln!(
                                     console,
@@ -139,23 +494,31 @@ impl BasicMenu {
                         }
                     }
                     BootOptionKind::Multiple(k) => {
+                        let default = match default {
+                            Some(BootOptionSelection::Multiple(which)) => *which as usize,
+                            _ => 0,
+                        };
                         writeln!(console, "{}:", i.name).core_err()?;
                         for (i, item) in k.iter().enumerate() {
                             // Indent is intentional.
                             writeln!(
                                 console,
                                 "{} {}. {}",
-                                if i == 0 { "*" } else { " " },
+                                if i == default { "*" } else { " " },
                                 i + 1,
                                 item.name
                             )
                             .core_err()?;
                         }
 
-                        write!(console, "Select: ").core_err()?;
-                        console.read_line(&mut buf)?;
+                        console.edit_line(&mut buf, "Select: ")?;
+                        let buf = buf.trim();
+                        if buf.is_empty() {
+                            ret.push(BootOptionSelection::Multiple(default as i32));
+                            break;
+                        }
                         if let Ok(num) = buf.parse::<i32>() {
-                            if num < 0 || num > k.len() as i32 {
+                            if num < 1 || num > k.len() as i32 {
                                 writeln!(console, "Invalid option.").core_err()?;
                                 continue;
                             }
@@ -167,21 +530,30 @@ impl BasicMenu {
                         }
                     }
                     BootOptionKind::Template(k) => {
-                        write!(
-                            console,
+                        if let Some(BootOptionSelection::Template(Some(value))) = default {
+                            buf.push_str(value);
+                        }
+                        let prompt = format!(
                             "{} ({})= ",
                             i.name,
                             i.description.as_deref().unwrap_or("no description")
-                        )
-                        .core_err()?;
-                        console.read_line(&mut buf)?;
-                        ret.push(BootOptionSelection::Template(Some(buf.clone())));
+                        );
+                        console.edit_line(&mut buf, &prompt)?;
+                        // Leave the template out if nothing is given.
+                        let value = buf.trim();
+                        ret.push(BootOptionSelection::Template(
+                            (!value.is_empty()).then(|| value.to_owned()),
+                        ));
+                        break;
+                    }
+                    _ => {
+                        // Unknown options from newer config are skipped.
+                        ret.push(BootOptionSelection::Default);
                         break;
                     }
-                    _ => unreachable!(),
                 }
             }
         }
-        Ok(())
+        Ok(ret)
     }
 }
@@ -0,0 +1,273 @@
+//! File browser over all volumes, to boot an image by hand.
+//!
+//! `Up`/`Down` moves, `Enter` opens a volume, directory or file, `Backspace` goes up,
+//! and `Esc` returns to menu. An `.efi` file is chainloaded, and a kernel is booted
+//! through its EFI stub after asking for initrd and cmdline.
+
+use alloc::borrow::ToOwned;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use anyhow::{anyhow, Result};
+
+use crate::boot::boot::BootAble;
+use crate::platform::efi::boot::EFIBoot;
+use crate::platform::efi::fs::EfiVolumes;
+use crate::platform::ToError;
+use io::complete::PathCompleter;
+use io::console::{AcceleratorKey, Console, CursorStyle, Key};
+use io::file::DirEntry;
+use io::fs::{path, VolumeInfo, Volumes};
+use io::LineEdit;
+
+/// File names of Linux kernels, in lower case.
+const KERNEL_NAMES: &[&str] = &["vmlinuz", "vmlinux", "bzimage", "kernel", "linux"];
+
+fn is_efi(name: &str) -> bool {
+    name.to_ascii_lowercase().ends_with(".efi")
+}
+
+fn is_kernel(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    KERNEL_NAMES.iter().any(|x| name.starts_with(x))
+}
+
+/// Size in a short human readable form.
+fn human_size(size: u64) -> String {
+    const UNITS: &[&str] = &["B", "K", "M", "G", "T"];
+    let mut size = size;
+    let mut unit = 0;
+    while size >= 10 * 1024 && unit + 1 < UNITS.len() {
+        size /= 1024;
+        unit += 1;
+    }
+    format!("{}{}", size, UNITS[unit])
+}
+
+/// A row of the list.
+enum Row {
+    Volume(usize),
+    Parent,
+    Entry(DirEntry),
+}
+
+pub struct FileBrowser {
+    platform: EfiVolumes,
+    volumes: Vec<VolumeInfo>,
+    /// Current volume and directory, or `None` when choosing a volume.
+    location: Option<(usize, String)>,
+    selected: usize,
+    /// First row shown.
+    top: usize,
+}
+
+impl FileBrowser {
+    pub fn new() -> Result<Self> {
+        let mut platform = EfiVolumes::new()?;
+        let volumes = platform.volumes()?;
+        if volumes.is_empty() {
+            return Err(anyhow!("no volume found."));
+        }
+        Ok(Self {
+            platform,
+            volumes,
+            location: None,
+            selected: 0,
+            top: 0,
+        })
+    }
+
+    fn rows(&mut self) -> Result<Vec<Row>> {
+        let Some((volume, path)) = &self.location else {
+            return Ok((0..self.volumes.len()).map(Row::Volume).collect());
+        };
+        let mut entries = self.platform.mount(*volume)?.read_dir(path)?;
+        // Directories go first.
+        entries.sort_by(|a, b| {
+            b.is_dir
+                .cmp(&a.is_dir)
+                .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+        });
+        let mut ret = Vec::with_capacity(entries.len() + 1);
+        ret.push(Row::Parent);
+        ret.extend(entries.into_iter().map(Row::Entry));
+        Ok(ret)
+    }
+
+    fn title(&self) -> String {
+        match &self.location {
+            None => "Choose a volume:".to_owned(),
+            Some((volume, path)) => {
+                let volume = &self.volumes[*volume];
+                let name = if volume.label.is_empty() {
+                    volume.device_path.as_str()
+                } else {
+                    volume.label.as_str()
+                };
+                format!("{}:{}", name, path)
+            }
+        }
+    }
+
+    fn row_text(&self, row: &Row) -> String {
+        match row {
+            Row::Volume(n) => {
+                let volume = &self.volumes[*n];
+                format!("{:<16} {}", volume.label, volume.device_path)
+            }
+            Row::Parent => "..".to_owned(),
+            Row::Entry(DirEntry {
+                name, is_dir: true, ..
+            }) => format!("{}\\", name),
+            Row::Entry(DirEntry { name, size, .. }) => {
+                format!("{:<40} {:>8}", name, human_size(*size))
+            }
+        }
+    }
+
+    fn draw(&mut self, rows: &[Row], console: &mut dyn Console) -> Result<()> {
+        let (width, height) = console.terminal_info()?.size;
+        // Title and help take a line each.
+        let page = height.saturating_sub(2).max(1);
+        if self.selected < self.top {
+            self.top = self.selected;
+        } else if self.selected >= self.top + page {
+            self.top = self.selected + 1 - page;
+        }
+        console.clear()?;
+        let mut title = self.title();
+        title.truncate(width.saturating_sub(1));
+        console.write_str(&title).core_err()?;
+        for (n, row) in rows.iter().enumerate().skip(self.top).take(page) {
+            console.set_cursor(0, (n - self.top + 1) as i32)?;
+            let marker = if n == self.selected { "> " } else { "  " };
+            let mut text = format!("{}{}", marker, self.row_text(row));
+            text.truncate(width.saturating_sub(1));
+            console.write_str(&text).core_err()?;
+        }
+        console.set_cursor(0, (height - 1) as i32)?;
+        console
+            .write_str("Enter: open  Backspace: up  Esc: back to menu")
+            .core_err()
+    }
+
+    /// Go to parent directory, or volume list from root.
+    fn up(&mut self) {
+        self.location = match self.location.take() {
+            Some((volume, dir)) if dir != "\\" => Some((volume, path::parent(&dir))),
+            _ => None,
+        };
+        self.selected = 0;
+        self.top = 0;
+    }
+
+    /// Browse until user leaves, or an image is booted.
+    pub fn run(&mut self, console: &mut dyn Console) -> Result<()> {
+        console.set_cursor_style(&CursorStyle::None)?;
+        loop {
+            let rows = match self.rows() {
+                Ok(rows) => rows,
+                Err(e) => {
+                    writeln!(console, "Failed to list {}: {}", self.title(), e).core_err()?;
+                    console.wait_for_key()?;
+                    self.up();
+                    continue;
+                }
+            };
+            self.selected = self.selected.min(rows.len().saturating_sub(1));
+            self.draw(&rows, console)?;
+            let page = console.terminal_info()?.size.1.saturating_sub(2).max(1);
+            match console.wait_for_key()? {
+                Key::Accelerator(AcceleratorKey::Up) => {
+                    self.selected = self.selected.saturating_sub(1)
+                }
+                Key::Accelerator(AcceleratorKey::Down) => {
+                    self.selected = (self.selected + 1).min(rows.len().saturating_sub(1))
+                }
+                Key::Accelerator(AcceleratorKey::PgUp) => {
+                    self.selected = self.selected.saturating_sub(page)
+                }
+                Key::Accelerator(AcceleratorKey::PgDn) => {
+                    self.selected = (self.selected + page).min(rows.len().saturating_sub(1))
+                }
+                Key::Accelerator(AcceleratorKey::Home) => self.selected = 0,
+                Key::Accelerator(AcceleratorKey::End) => {
+                    self.selected = rows.len().saturating_sub(1)
+                }
+                Key::Accelerator(AcceleratorKey::Backspace | AcceleratorKey::Left) => self.up(),
+                Key::Accelerator(AcceleratorKey::Esc) => break,
+                Key::Accelerator(AcceleratorKey::Enter | AcceleratorKey::Right) => {
+                    match rows.get(self.selected) {
+                        Some(Row::Volume(n)) => {
+                            self.location = Some((*n, "\\".to_owned()));
+                            self.selected = 0;
+                        }
+                        Some(Row::Parent) => self.up(),
+                        Some(Row::Entry(entry)) if entry.is_dir => {
+                            if let Some((_, dir)) = &mut self.location {
+                                *dir = path::join(dir, &entry.name);
+                            }
+                            self.selected = 0;
+                        }
+                        Some(Row::Entry(entry)) => {
+                            console.clear()?;
+                            if let Err(e) = self.boot_file(&entry.name, console) {
+                                writeln!(console, "Failed to boot: {}", e).core_err()?;
+                                writeln!(console, "Press any key to continue.").core_err()?;
+                                console.wait_for_key()?;
+                            }
+                        }
+                        None => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+        console.clear()
+    }
+
+    /// Boot file `name` in current directory.
+    ///
+    /// Returns if the file is not booted.
+    fn boot_file(&mut self, name: &str, console: &mut dyn Console) -> Result<()> {
+        let Some((volume, dir)) = &self.location else {
+            return Ok(());
+        };
+        let index = *volume;
+        let volume = &self.volumes[index];
+        let path = path::join(dir, name);
+        if is_efi(name) {
+            writeln!(console, "Booting {}", path).core_err()?;
+            EFIBoot::on_device(&volume.device_path, &path, None).boot()?;
+        }
+        if !is_kernel(name) {
+            let mut buf = String::new();
+            // Name is on its own line, as a long prompt leaves no room for editing.
+            writeln!(console, "{}", name).core_err()?;
+            console.edit_line(&mut buf, "Boot as a Linux kernel? (y/N) ")?;
+            if !buf.trim().eq_ignore_ascii_case("y") {
+                return Ok(());
+            }
+        }
+        // Initrd is loaded by EFI stub, from the volume of kernel.
+        let mut fs = self.platform.mount(index)?;
+        let mut completer = PathCompleter::new(|x: &str| fs.read_dir(x));
+        let mut initrd = dir.clone();
+        if !initrd.ends_with('\\') {
+            initrd.push('\\');
+        }
+        console.edit_line_complete(&mut initrd, "initrd (empty for none): ", &mut completer)?;
+        let mut cmdline = String::new();
+        console.edit_line(&mut cmdline, "cmdline: ")?;
+        let initrd = initrd.trim();
+        let mut args = Vec::new();
+        if !initrd.is_empty() && !initrd.ends_with(['\\', '/']) {
+            args.push(format!("initrd={}", initrd.replace('/', "\\")));
+        }
+        if !cmdline.trim().is_empty() {
+            args.push(cmdline.trim().to_owned());
+        }
+        writeln!(console, "Booting {}", path).core_err()?;
+        EFIBoot::on_device(&volume.device_path, &path, Some(&args.join(" "))).boot()?
+    }
+}
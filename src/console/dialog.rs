@@ -0,0 +1,36 @@
+//! Message boxes, shown before anything else goes on screen.
+
+use alloc::format;
+use alloc::string::String;
+use anyhow::{anyhow, Result};
+
+use io::console::{Console, CursorStyle};
+
+/// Write a line, cut to `width` so it does not wrap.
+fn write_line(console: &mut dyn Console, line: &str, width: usize) -> Result<()> {
+    let line: String = line.chars().take(width.saturating_sub(1)).collect();
+    console
+        .write_str(&format!("{}\r\n", line))
+        .map_err(|_| anyhow!("failed to write!"))
+}
+
+/// Show `title` and `text` on a cleared screen, and wait for a key.
+///
+/// Text is cut to fit the screen, so the prompt stays visible.
+pub fn message(console: &mut dyn Console, title: &str, text: &str) -> Result<()> {
+    let (width, height) = console.terminal_info()?.size;
+    console.set_cursor_style(&CursorStyle::None)?;
+    console.clear()?;
+    write_line(console, title, width)?;
+    write_line(console, "", width)?;
+    // Title, blank lines and prompt take 4 lines.
+    for line in text.lines().take(height.saturating_sub(4)) {
+        write_line(console, line, width)?;
+    }
+    write_line(console, "", width)?;
+    console
+        .write_str("Press any key to continue.")
+        .map_err(|_| anyhow!("failed to write!"))?;
+    console.wait_for_key()?;
+    console.clear()
+}
@@ -1,7 +1,7 @@
 use anyhow::Result;
 
-use crate::io::console::Console;
 use config::boot::{BootItem, Config};
+use io::console::Console;
 
 /// Show menu, then retrieve user selection.
 ///
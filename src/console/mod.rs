@@ -1,6 +1,11 @@
 pub mod ansi;
 pub mod basicmenu;
+#[cfg(target_os = "uefi")]
+pub mod browser;
 pub mod console;
+pub mod dialog;
 pub mod menu;
 pub mod serial;
+#[cfg(target_os = "uefi")]
+pub mod shell;
 pub mod tty;
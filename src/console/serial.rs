@@ -1,7 +1,7 @@
 use anyhow::{anyhow, Result};
 use uefi::{prelude::BootServices, proto::console::serial::Serial, table::boot::ScopedProtocol};
 
-use crate::io::Stream;
+use io::Stream;
 
 /// Serial Console-specific methods
 pub trait SerialConsole: Stream {
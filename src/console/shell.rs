@@ -0,0 +1,369 @@
+//! Rescue command shell.
+//!
+//! A few commands to look around and boot a machine by hand, when config is broken.
+//! Type `help` for the list of commands.
+
+use alloc::borrow::ToOwned;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use anyhow::{anyhow, Context, Result};
+use config::{BootTarget, ImageLocation};
+use uefi::table::runtime::VariableVendor;
+
+use crate::config::do_boot;
+use crate::platform::efi::block::list_blocks;
+use crate::platform::efi::file::list_dir;
+use crate::platform::efi::fs::mount_path;
+use crate::platform::efi::var::{delete_vendor_var, get_vendor_var, set_vendor_var};
+use crate::platform::efi::B2_VENDOR;
+use crate::platform::ToError;
+use io::complete::{Complete, PathCompleter};
+use io::console::Console;
+use io::file::DirEntry;
+use io::fs::FileSystem;
+use io::LineEdit;
+
+const HELP: &str = "\
+ls [path]                   list a directory
+cat <path>                  print a file
+hexdump <path> [off [len]]  dump a file in hex
+lsblk                       list disks and partitions
+boot efi <path> [cmdline]   boot an EFI image
+linux <path> [cmdline]      set kernel and its cmdline
+initrd <path>...            set initrds
+devicetree [path]           set device tree given to kernel, or unset it
+boot                        boot kernel set by `linux`
+getvar [-g] <name>          dump a variable
+setvar [-g] [-x] <name> <value>
+                            set a variable to text, or hex bytes with -x
+delvar [-g] <name>          delete a variable
+reboot, poweroff, fwsetup   reset the machine
+config reload               load config again, and return to menu
+exit                        return to menu
+
+Paths are on the volume b2 was loaded from, or on another one as LABEL=<label>:<path>,
+PARTUUID=<guid>:<path>, #<n>:<path> or <device path>:<path>. Variables are of b2, or global
+ones with -g.";
+
+const COMMANDS: &[&str] = &[
+    "boot",
+    "cat",
+    "config",
+    "delvar",
+    "devicetree",
+    "exit",
+    "fwsetup",
+    "getvar",
+    "help",
+    "hexdump",
+    "initrd",
+    "linux",
+    "ls",
+    "lsblk",
+    "poweroff",
+    "reboot",
+    "setvar",
+];
+
+/// How the shell is left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellExit {
+    /// Return to menu.
+    Menu,
+    /// Load config again, then return to menu.
+    ReloadConfig,
+}
+
+/// Split a command line into words.
+///
+/// Words are separated by whitespace, and double quotes group a word with spaces.
+/// Backslash is not special, as it is the path separator.
+fn split_words(line: &str) -> Result<Vec<String>> {
+    let mut ret = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                word.get_or_insert_with(String::new);
+            }
+            c if c.is_whitespace() && !quoted => ret.extend(word.take()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quoted {
+        return Err(anyhow!("unterminated quote."));
+    }
+    ret.extend(word);
+    Ok(ret)
+}
+
+/// Parse a number, in hex with `0x` prefix.
+fn parse_number(s: &str) -> Result<usize> {
+    match s.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+    .map_err(|_| anyhow!("invalid number {}.", s))
+}
+
+fn parse_hex(s: &str) -> Result<Vec<u8>> {
+    let s: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+    if s.len() % 2 != 0 {
+        return Err(anyhow!("odd number of hex digits."));
+    }
+    let digit = |c: u8| (c as char).to_digit(16).map(|x| x as u8);
+    s.as_bytes()
+        .chunks(2)
+        .map(|x| {
+            digit(x[0])
+                .zip(digit(x[1]))
+                .map(|(high, low)| high << 4 | low)
+                .ok_or_else(|| anyhow!("invalid hex {}.", String::from_utf8_lossy(x)))
+        })
+        .collect()
+}
+
+/// Write `data` in lines of 16 bytes, with offsets starting from `base`.
+fn hexdump(console: &mut dyn Console, data: &[u8], base: usize) -> Result<()> {
+    for (n, line) in data.chunks(16).enumerate() {
+        let mut text = format!("{:08x} ", base + n * 16);
+        for i in 0..16 {
+            match line.get(i) {
+                Some(b) => text.push_str(&format!(" {:02x}", b)),
+                None => text.push_str("   "),
+            }
+        }
+        text.push_str("  |");
+        text.extend(line.iter().map(|&b| {
+            if b.is_ascii_graphic() || b == b' ' {
+                b as char
+            } else {
+                '.'
+            }
+        }));
+        text.push('|');
+        writeln!(console, "{}", text).core_err()?;
+    }
+    Ok(())
+}
+
+/// Command names for the first word, and paths for the rest.
+struct ShellCompleter<F> {
+    paths: PathCompleter<F>,
+}
+
+impl<F: FnMut(&str) -> Result<Vec<DirEntry>>> Complete for ShellCompleter<F> {
+    fn complete(&mut self, word: &str) -> Vec<String> {
+        if word.contains(['/', '\\']) {
+            return self.paths.complete(word);
+        }
+        COMMANDS
+            .iter()
+            .filter(|x| x.starts_with(word))
+            .map(|&x| x.to_owned())
+            .collect()
+    }
+}
+
+pub struct Shell {
+    /// Kernel and cmdline set by `linux`.
+    kernel: Option<(String, String)>,
+    /// Initrds set by `initrd`.
+    initrd: Vec<String>,
+    /// Device tree set by `devicetree`.
+    devicetree: Option<String>,
+}
+
+impl Shell {
+    pub fn new() -> Self {
+        Self {
+            kernel: None,
+            initrd: Vec::new(),
+            devicetree: None,
+        }
+    }
+
+    /// Read and run commands, until the shell is left.
+    pub fn run(&mut self, console: &mut dyn Console) -> Result<ShellExit> {
+        writeln!(console, "b2 rescue shell. Type `help` for commands.").core_err()?;
+        let mut completer = ShellCompleter {
+            paths: PathCompleter::new(list_dir),
+        };
+        loop {
+            let mut buf = String::new();
+            console.edit_line_complete(&mut buf, "b2> ", &mut completer)?;
+            let words = match split_words(&buf) {
+                Ok(words) => words,
+                Err(e) => {
+                    writeln!(console, "error: {}", e).core_err()?;
+                    continue;
+                }
+            };
+            let words: Vec<&str> = words.iter().map(String::as_str).collect();
+            match self.execute(&words, console) {
+                Ok(Some(exit)) => return Ok(exit),
+                Ok(None) => {}
+                Err(e) => writeln!(console, "error: {:#}", e).core_err()?,
+            }
+        }
+    }
+
+    /// Run a command. Returns `Some` if the shell should be left.
+    fn execute(&mut self, words: &[&str], console: &mut dyn Console) -> Result<Option<ShellExit>> {
+        let Some((&command, args)) = words.split_first() else {
+            return Ok(None);
+        };
+        match (command, args) {
+            ("help", _) => writeln!(console, "{}", HELP).core_err()?,
+            ("exit", _) => return Ok(Some(ShellExit::Menu)),
+            ("config", ["reload"]) => return Ok(Some(ShellExit::ReloadConfig)),
+            ("ls", []) => self.ls("", console)?,
+            ("ls", [path]) => self.ls(path, console)?,
+            ("cat", [path]) => {
+                let (mut fs, path) = mount_path(path)?;
+                let data = fs.read(&path)?;
+                writeln!(console, "{}", String::from_utf8_lossy(&data)).core_err()?;
+            }
+            ("hexdump", [path, range @ ..]) if range.len() <= 2 => {
+                let (mut fs, path) = mount_path(path)?;
+                let mut file = fs.open(&path)?;
+                let size = file.size() as usize;
+                let offset = range.first().map(|x| parse_number(x)).transpose()?;
+                let offset = offset.unwrap_or(0).min(size);
+                let len = range.get(1).map(|x| parse_number(x)).transpose()?;
+                let len = len.map_or(size - offset, |x| x.min(size - offset));
+                // Only the range asked for is read, so dumping the head of a large image is quick.
+                let mut data = Vec::new();
+                data.try_reserve(len)
+                    .map_err(|_| anyhow!("range too large to fit in memory."))?;
+                data.resize(len, 0);
+                let n = file.read_at(offset as u64, &mut data)?;
+                hexdump(console, &data[..n], offset)?;
+            }
+            ("lsblk", []) => self.lsblk(console)?,
+            ("boot", ["efi", path, cmdline @ ..]) => {
+                let target = BootTarget::EFI {
+                    path: (*path).to_owned(),
+                    cmdline: (!cmdline.is_empty()).then(|| cmdline.join(" ")),
+                };
+                do_boot(&target)?;
+            }
+            ("boot", []) => {
+                let target = self.linux_target()?;
+                do_boot(&target)?;
+            }
+            ("linux", [path, cmdline @ ..]) => {
+                self.kernel = Some(((*path).to_owned(), cmdline.join(" ")));
+            }
+            ("initrd", paths) => self.initrd = paths.iter().map(|&x| x.to_owned()).collect(),
+            ("devicetree", []) => self.devicetree = None,
+            ("devicetree", [path]) => self.devicetree = Some((*path).to_owned()),
+            ("getvar", args) => {
+                let (vendor, args) = Self::vendor(args);
+                let [name] = args else {
+                    return Err(anyhow!("usage: getvar [-g] <name>"));
+                };
+                hexdump(console, &get_vendor_var(name, &vendor)?, 0)?;
+            }
+            ("setvar", args) => {
+                let (vendor, args) = Self::vendor(args);
+                let data = match args {
+                    ["-x", _, value] => parse_hex(value)?,
+                    [_, value] => value.as_bytes().to_owned(),
+                    _ => return Err(anyhow!("usage: setvar [-g] [-x] <name> <value>")),
+                };
+                set_vendor_var(args[args.len() - 2], &vendor, &data)?;
+            }
+            ("delvar", args) => {
+                let (vendor, args) = Self::vendor(args);
+                let [name] = args else {
+                    return Err(anyhow!("usage: delvar [-g] <name>"));
+                };
+                delete_vendor_var(name, &vendor)?;
+            }
+            ("reboot", []) => {
+                do_boot(&BootTarget::Reboot)?;
+            }
+            ("poweroff", []) => {
+                do_boot(&BootTarget::Poweroff)?;
+            }
+            ("fwsetup", []) => {
+                do_boot(&BootTarget::FirmwareSetup)?;
+            }
+            _ if COMMANDS.contains(&command) => {
+                return Err(anyhow!("invalid arguments of {}, see `help`.", command))
+            }
+            _ => return Err(anyhow!("unknown command {}, see `help`.", command)),
+        }
+        Ok(None)
+    }
+
+    /// Take `-g` from arguments, which selects EFI global variables.
+    fn vendor<'a, 'b>(args: &'a [&'b str]) -> (VariableVendor, &'a [&'b str]) {
+        match args {
+            ["-g", rest @ ..] => (VariableVendor::GLOBAL_VARIABLE, rest),
+            _ => (B2_VENDOR, args),
+        }
+    }
+
+    /// Linux kernel set by `linux` and `initrd`, booted through its EFI stub.
+    fn linux_target(&self) -> Result<BootTarget> {
+        let (kernel, cmdline) = self
+            .kernel
+            .as_ref()
+            .ok_or_else(|| anyhow!("no kernel, set it with `linux` first."))?;
+        let path = |x: &String| ImageLocation::Path { path: x.clone() };
+        Ok(BootTarget::Linux {
+            kernel: path(kernel),
+            initrd: self.initrd.iter().map(path).collect(),
+            cmdline: cmdline.clone(),
+            devicetree: self.devicetree.as_ref().map(path),
+        })
+    }
+
+    fn ls(&self, path: &str, console: &mut dyn Console) -> Result<()> {
+        let mut entries = mount_path(path)
+            .and_then(|(mut fs, dir)| fs.read_dir(&dir))
+            .with_context(|| format!("failed to list {}", path))?;
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        for i in entries {
+            if i.is_dir {
+                writeln!(console, "{:>12}  {}\\", "<DIR>", i.name)
+            } else {
+                writeln!(console, "{:>12}  {}", i.size, i.name)
+            }
+            .core_err()?;
+        }
+        Ok(())
+    }
+
+    fn lsblk(&self, console: &mut dyn Console) -> Result<()> {
+        for (n, i) in list_blocks()?.iter().enumerate() {
+            let kind = if i.partition { "part" } else { "disk" };
+            let mut flags = String::new();
+            if i.removable {
+                flags.push_str(" removable");
+            }
+            if !i.present {
+                flags.push_str(" no-media");
+            }
+            if i.file_system {
+                flags.push_str(" fs");
+            }
+            writeln!(
+                console,
+                "{:>3} {} {:>8} MiB{}\n    {}",
+                n,
+                kind,
+                i.size() >> 20,
+                flags,
+                i.path
+            )
+            .core_err()?;
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1 @@
-pub mod console;
 pub mod file;
-mod meta;
-pub use meta::{BlockDevice, EditConfig, LineEdit, Read, ReadOne, ReadSecret, ReadString, Stream};
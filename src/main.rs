@@ -10,7 +10,7 @@
 #![feature(never_type)]
 #![feature(cfg_match)]
 
-// b2 has not been tested on architectures other than x86_64.
+// b2 has not been tested on architectures other than x86_64 and aarch64.
 
 #[cfg(not(target_os = "uefi"))]
 compile_error!("b2 only supports EFI targets for now.");
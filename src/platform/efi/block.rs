@@ -0,0 +1,110 @@
+//! Block devices known to firmware.
+
+use alloc::{string::String, vec::Vec};
+use anyhow::{anyhow, Result};
+use uefi::{
+    proto::{
+        device_path::{
+            text::{AllowShortcuts, DevicePathToText, DisplayOnly},
+            DevicePath,
+        },
+        media::{block::BlockIO, fs::SimpleFileSystem},
+    },
+    table::boot::{BootServices, OpenProtocolAttributes, OpenProtocolParams, ScopedProtocol},
+    Handle,
+};
+use uefi_services::system_table;
+
+use crate::platform::ToError;
+
+/// A block device, which is a whole disk or a partition.
+#[derive(Debug, Clone)]
+pub struct BlockInfo {
+    pub handle: Handle,
+    pub media_id: u32,
+    pub block_size: u32,
+    pub last_block: u64,
+    pub partition: bool,
+    pub removable: bool,
+    pub present: bool,
+    /// Whether a file system is mounted on the device.
+    pub file_system: bool,
+    /// Device path in text form.
+    pub path: String,
+}
+
+impl BlockInfo {
+    /// Size in bytes.
+    pub fn size(&self) -> u64 {
+        (self.last_block + 1) * self.block_size as u64
+    }
+}
+
+/// Open a protocol without taking it from drivers.
+///
+/// Protocols opened exclusively are disconnected from their drivers, which would unmount file systems.
+pub(crate) fn get_protocol<'a, P: uefi::proto::ProtocolPointer + ?Sized>(
+    bs: &'a BootServices,
+    handle: Handle,
+) -> Result<ScopedProtocol<'a, P>> {
+    unsafe {
+        bs.open_protocol::<P>(
+            OpenProtocolParams {
+                handle,
+                agent: bs.image_handle(),
+                controller: None,
+            },
+            OpenProtocolAttributes::GetProtocol,
+        )
+    }
+    .core_err()
+}
+
+/// Text form of the device path of `handle`.
+pub fn device_path_text(handle: Handle) -> Result<String> {
+    let st = system_table();
+    let bs = st.boot_services();
+    let path = get_protocol::<DevicePath>(bs, handle)?;
+    path_text(&path)
+}
+
+/// Text form of a device path.
+pub fn path_text(path: &DevicePath) -> Result<String> {
+    let st = system_table();
+    let bs = st.boot_services();
+    let to_text = bs
+        .get_handle_for_protocol::<DevicePathToText>()
+        .core_err()?;
+    let to_text = get_protocol::<DevicePathToText>(bs, to_text)?;
+    let text = to_text
+        .convert_device_path_to_text(bs, path, DisplayOnly(false), AllowShortcuts(false))
+        .core_err()?;
+    let mut ret = String::new();
+    text.as_str_in_buf(&mut ret)
+        .map_err(|_| anyhow!("Failed to convert to rust string."))?;
+    Ok(ret)
+}
+
+/// List all block devices.
+pub fn list_blocks() -> Result<Vec<BlockInfo>> {
+    let st = system_table();
+    let bs = st.boot_services();
+    let file_systems = bs.find_handles::<SimpleFileSystem>().unwrap_or_default();
+    let mut ret = Vec::new();
+    for handle in bs.find_handles::<BlockIO>().core_err()? {
+        let block = get_protocol::<BlockIO>(bs, handle)?;
+        let media = block.media();
+        ret.push(BlockInfo {
+            handle,
+            media_id: media.media_id(),
+            block_size: media.block_size(),
+            last_block: media.last_block(),
+            partition: media.is_logical_partition(),
+            removable: media.is_removable_media(),
+            present: media.is_media_present(),
+            file_system: file_systems.contains(&handle),
+            path: device_path_text(handle).unwrap_or_default(),
+        });
+    }
+    Ok(ret)
+}
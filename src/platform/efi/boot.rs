@@ -14,7 +14,7 @@ use uefi::{
             self,
             build::DevicePathBuilder,
             text::{DevicePathFromText, DevicePathToText},
-            DevicePath, FfiDevicePath,
+            FfiDevicePath,
         },
         loaded_image::LoadedImage,
     },
@@ -30,6 +30,9 @@ use config::BootTarget;
 use crate::platform::PlatformFile;
 
 use crate::boot::boot::BootAble;
+use crate::platform::efi::block::device_path_text;
+use crate::platform::efi::fs::resolve_volume;
+use io::fs::{VolumeAddress, VolumePath};
 
 pub struct EFIBoot {
     path: String,
@@ -52,6 +55,28 @@ impl EFIBoot {
             device: None,
         }
     }
+
+    /// Image at `path` on another device, given as device path text.
+    pub fn on_device(device: &str, path: &str, cmdline: Option<&str>) -> Self {
+        Self {
+            device: Some(device.to_owned()),
+            ..Self::create(path, cmdline)
+        }
+    }
+
+    /// Image at a path which may name its volume, such as `LABEL=boot:/vmlinuz`.
+    pub fn locate(location: &str, cmdline: Option<&str>) -> anyhow::Result<Self> {
+        let location = VolumePath::parse(location)?;
+        if location.volume == VolumeAddress::Current {
+            return Ok(Self::create(&location.path, cmdline));
+        }
+        let device = resolve_volume(&location.volume)?;
+        Ok(Self::on_device(
+            &device_path_text(device)?,
+            &location.path,
+            cmdline,
+        ))
+    }
 }
 
 impl BootAble for EFIBoot {
@@ -71,14 +96,6 @@ impl BootAble for EFIBoot {
         let device_path_from_text = bs
             .open_protocol_exclusive::<DevicePathFromText>(device_path_from_text)
             .core_err()?;
-        let this_image = bs.image_handle();
-        let image_protocol = bs
-            .open_protocol_exclusive::<LoadedImage>(this_image)
-            .core_err()?;
-        let root_device_handle = image_protocol.device().unwrap();
-        let root_device = bs
-            .open_protocol_exclusive::<DevicePath>(root_device_handle)
-            .core_err()?;
         let device_path_to_text = bs
             .get_handle_for_protocol::<DevicePathToText>()
             .core_err()?;
@@ -94,22 +111,20 @@ impl BootAble for EFIBoot {
             .core_err()
             .context("Failed to convert!")?;
 
-        // We have to join two string of device path, as uefi-rs does not implement DevicePathUtil.
-        let root_device = device_path_to_text
-            .convert_device_path_to_text(
-                bs,
-                &root_device,
-                DisplayOnly(false),
-                AllowShortcuts(false),
-            )
-            .core_err()?;
         let file_device = device_path_to_text
             .convert_device_path_to_text(bs, file_path, DisplayOnly(false), AllowShortcuts(false))
             .core_err()?;
-        let mut full_path = String::new();
-        root_device
-            .as_str_in_buf(&mut full_path)
-            .map_err(|_| anyhow!("Failed to convert to rust string."))?;
+        // We have to join two string of device path, as uefi-rs does not implement DevicePathUtil.
+        // Without a device, image is loaded from the device of b2.
+        let mut full_path = match self.device.as_ref() {
+            Some(device) => device.clone(),
+            None => {
+                let image_protocol = bs
+                    .open_protocol_exclusive::<LoadedImage>(bs.image_handle())
+                    .core_err()?;
+                device_path_text(image_protocol.device().unwrap())?
+            }
+        };
         full_path.push('/');
         file_device
             .as_str_in_buf(&mut full_path)
@@ -163,7 +178,7 @@ pub fn boot(target: &BootTarget) -> anyhow::Result<bool> {
     match target {
         BootTarget::EFI { path, cmdline } => {
             let cmdline = cmdline.as_ref().map(|x| x.as_ref());
-            let mut boot = EFIBoot::create(path, cmdline);
+            let mut boot = EFIBoot::locate(path, cmdline)?;
             boot.boot()?;
         }
         BootTarget::FirmwareSetup => {
@@ -197,9 +212,14 @@ pub fn boot(target: &BootTarget) -> anyhow::Result<bool> {
             kernel,
             initrd,
             cmdline,
-        } => Err(anyhow!(
-            "Loading Linux kernel directly is unavailable for now."
-        )),
+            devicetree,
+        } => crate::platform::efi::linux::boot(kernel, initrd, cmdline, devicetree.as_ref())?,
+        #[cfg(target_arch = "x86_64")]
+        BootTarget::Multiboot2 {
+            kernel,
+            cmdline,
+            modules,
+        } => crate::platform::efi::multiboot2::boot(kernel, cmdline, modules)?,
         BootTarget::Poweroff => {
             let st = system_table();
             let rs = st.runtime_services();
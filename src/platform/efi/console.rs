@@ -7,30 +7,138 @@ use anyhow::{anyhow, Context, Result};
 use uefi::{
     prelude::BootServices,
     proto::console::text::{Input, Output},
-    table::{boot::ScopedProtocol, Boot, SystemTable},
-    Event,
+    table::{
+        boot::{
+            EventType, OpenProtocolAttributes, OpenProtocolParams, ScopedProtocol, TimerTrigger,
+            Tpl,
+        },
+        Boot, SystemTable,
+    },
+    Event, Handle,
 };
 use uefi_services::system_table;
 
-use crate::{
-    io::{
-        console::{AcceleratorKey, Console, CursorStyle, Key, TerminalInfo},
-        ReadOne, ReadSecret,
-    },
-    platform::efi::efi_error::ToError,
+use crate::platform::efi::{
+    efi_error::ToError,
+    input::InputEx,
+    tty::{key_of, modifier_of},
+};
+use io::{
+    console::{AcceleratorKey, Console, CursorStyle, Key, ModifierKey, TerminalInfo},
+    EditSession, ReadOne, ReadSecret,
 };
 
 /// UEFI text protocol based console.
-pub struct EFIConsole {}
+pub struct EFIConsole {
+    session: EditSession,
+}
 
 impl EFIConsole {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            session: EditSession::default(),
+        }
     }
 
     /// Acquire current EFI terminal.
     pub fn from_system_table() -> Self {
-        Self {}
+        Self::new()
+    }
+
+    /// Open Simple Text Input Ex on console input, if firmware supports it.
+    fn input_ex(bs: &BootServices) -> Option<ScopedProtocol<InputEx>> {
+        let st = system_table();
+        let raw = st.as_ptr() as *const uefi_raw::table::system::SystemTable;
+        let handle = unsafe { Handle::from_ptr((*raw).stdin_handle) }?;
+        // Console input is shared with firmware, so do not open exclusively.
+        unsafe {
+            bs.open_protocol::<InputEx>(
+                OpenProtocolParams {
+                    handle,
+                    agent: bs.image_handle(),
+                    controller: None,
+                },
+                OpenProtocolAttributes::GetProtocol,
+            )
+        }
+        .ok()
+    }
+
+    /// Keys pressed before now, and the modifier being held, for startup chords.
+    ///
+    /// Pending keys are consumed.
+    pub fn startup_keys(&mut self) -> (Vec<Key>, Option<ModifierKey>) {
+        // Keys typed ahead are bounded by firmware buffer, but be safe with broken firmware.
+        const MAX_PENDING: usize = 32;
+        let mut keys = Vec::new();
+        let st = system_table();
+        let Some(mut input) = Self::input_ex(st.boot_services()) else {
+            while let Ok(Some(key)) = self.read_key() {
+                keys.push(key);
+                if keys.len() == MAX_PENDING {
+                    break;
+                }
+            }
+            return (keys, None);
+        };
+        while keys.len() < MAX_PENDING {
+            let Ok(data) = input.read_key_state() else {
+                break;
+            };
+            if data.key.scan_code == 0 && data.key.unicode_char == 0 {
+                return (keys, modifier_of(data.key_state.key_shift_state));
+            }
+            keys.push(data.into());
+        }
+        (keys, None)
+    }
+
+    /// Wait for a key for at most `timeout_ms` milliseconds.
+    ///
+    /// Returns `None` on timeout.
+    pub fn wait_for_key_timeout(&mut self, timeout_ms: u64) -> Result<Option<Key>> {
+        let st = system_table();
+        let bs = st.boot_services();
+        let key_event = match Self::input_ex(bs) {
+            Some(input) => input.wait_for_key_event(),
+            None => system_table().stdin().wait_for_key_event(),
+        }
+        .ok_or(anyhow!("failed to read key."))?;
+        let timer = unsafe { bs.create_event(EventType::TIMER, Tpl::APPLICATION, None, None) }
+            .core_err()
+            .context("Failed to create timer.")?;
+        let waited = bs
+            .set_timer(&timer, TimerTrigger::Relative(timeout_ms * 10_000))
+            .core_err()
+            .and_then(|_| {
+                let timer = unsafe { timer.unsafe_clone() };
+                bs.wait_for_event(&mut [key_event, timer][..]).core_err()
+            });
+        bs.close_event(timer).core_err()?;
+        match waited.context("Failed to wait for event.")? {
+            0 => self.read_one().map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Read a key through Simple Text Input Ex, so modifiers are available.
+    fn read_key_ex(input: &mut InputEx) -> Result<Key> {
+        loop {
+            let event = input
+                .wait_for_key_event()
+                .ok_or(anyhow!("failed to read key."))?;
+            system_table()
+                .boot_services()
+                .wait_for_event(&mut [event][..])
+                .core_err()
+                .context("Failed to wait for event.")?;
+            let key = input.read_key().core_err().context("Failed to read key.")?;
+            // Pressing a modifier alone may report a key with neither char nor scan code.
+            match key {
+                Some(k) if k.key.scan_code != 0 || k.key.unicode_char != 0 => return Ok(k.into()),
+                _ => {}
+            }
+        }
     }
 }
 
@@ -44,6 +152,11 @@ impl Write for EFIConsole {
 
 impl ReadOne<Key> for EFIConsole {
     fn read_one(&mut self) -> Result<Key> {
+        let st = system_table();
+        if let Some(mut input) = Self::input_ex(st.boot_services()) {
+            return Self::read_key_ex(&mut input);
+        }
+
         let mut st = system_table();
         let mut input = st.stdin();
         let event = input
@@ -62,7 +175,7 @@ impl ReadOne<Key> for EFIConsole {
             .and_then(|x| x.ok_or_else(|| anyhow!("No key value!")))
             .context("Failed to read key.")?;
 
-        Ok(key.into())
+        Ok(key_of(key))
     }
 }
 
@@ -116,6 +229,20 @@ impl<'a> ReadSecret for EFIConsole {
 }
 
 impl Console for EFIConsole {
+    fn read_key(&mut self) -> Result<Option<Key>> {
+        let mut st = system_table();
+        let bs = st.boot_services();
+        if let Some(mut input) = Self::input_ex(bs) {
+            loop {
+                match input.read_key().core_err()? {
+                    Some(k) if k.key.scan_code == 0 && k.key.unicode_char == 0 => {}
+                    k => return Ok(k.map(Into::into)),
+                }
+            }
+        }
+        Ok(st.stdin().read_key().core_err()?.map(key_of))
+    }
+
     fn get_cursor(&mut self) -> Result<(i32, i32)> {
         let mut st = system_table();
         let stdout = st.stdout();
@@ -132,7 +259,7 @@ impl Console for EFIConsole {
         Ok(())
     }
 
-    fn set_cursor_style(&mut self, style: &crate::io::console::CursorStyle) -> Result<()> {
+    fn set_cursor_style(&mut self, style: &io::console::CursorStyle) -> Result<()> {
         let mut st = system_table();
         let stdout = st.stdout();
         stdout
@@ -148,6 +275,11 @@ impl Console for EFIConsole {
         Ok(())
     }
 
+    fn clear(&mut self) -> Result<()> {
+        let mut st = system_table();
+        st.stdout().clear().core_err()
+    }
+
     fn reset(&mut self) -> Result<()> {
         let mut st = system_table();
         let mut stdin = st.stdin();
@@ -161,6 +293,10 @@ impl Console for EFIConsole {
         Ok(())
     }
 
+    fn edit_session(&mut self) -> Option<&mut EditSession> {
+        Some(&mut self.session)
+    }
+
     fn terminal_info(&mut self) -> Result<TerminalInfo> {
         let mut st = system_table();
         let mut stdout = st.stdout();
@@ -0,0 +1,105 @@
+//! Third-party UEFI drivers, such as file system drivers.
+//!
+//! b2 reads no file system by itself. Drivers in `\EFI\b2\drivers\` of the volume b2 was loaded from
+//! are started, then all controllers are connected again, so firmware mounts file systems they provide.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use anyhow::{anyhow, Context, Result};
+use log::{info, warn};
+use uefi::proto::loaded_image::LoadedImage;
+use uefi::table::boot::{LoadImageSource, MemoryType, SearchType};
+use uefi_services::system_table;
+
+use crate::platform::efi::block::get_protocol;
+use crate::platform::efi::fs::EfiFileSystem;
+use crate::platform::ToError;
+use io::fs::{path, FileSystem};
+
+/// Directory of drivers, on the volume b2 was loaded from.
+pub const DRIVER_DIR: &str = "\\EFI\\b2\\drivers";
+
+/// Load and start a driver image.
+fn start_driver(data: &[u8]) -> Result<()> {
+    let st = system_table();
+    let bs = st.boot_services();
+    let image = bs
+        .load_image(
+            bs.image_handle(),
+            LoadImageSource::FromBuffer {
+                buffer: data,
+                file_path: None,
+            },
+        )
+        .core_err()
+        .context("failed to load image")?;
+    let code_type = get_protocol::<LoadedImage>(bs, image)?.code_type();
+    // An application would take over the machine instead of returning.
+    if code_type != MemoryType::BOOT_SERVICES_CODE && code_type != MemoryType::RUNTIME_SERVICES_CODE
+    {
+        let _ = bs.unload_image(image);
+        return Err(anyhow!("not a driver."));
+    }
+    bs.start_image(image).core_err().context("failed to start")
+}
+
+/// Connect drivers to all controllers, recursively.
+pub fn connect_all() -> Result<()> {
+    let st = system_table();
+    let bs = st.boot_services();
+    let handles = bs.locate_handle_buffer(SearchType::AllHandles).core_err()?;
+    for &handle in handles.iter() {
+        // Most handles are not controllers, or have no driver to connect.
+        let _ = bs.connect_controller(handle, None, None, true);
+    }
+    Ok(())
+}
+
+/// Start drivers in `DRIVER_DIR`, then connect them.
+///
+/// `names` selects drivers by file name, ignoring case. All `.efi` files are started if it is `None`.
+/// Returns the number of drivers started. A driver failing to start is logged and skipped.
+pub fn load_drivers(names: Option<&[String]>) -> Result<usize> {
+    if names.is_some_and(|x| x.is_empty()) {
+        return Ok(0);
+    }
+    let mut fs = EfiFileSystem::current()?;
+    let entries = match fs.read_dir(DRIVER_DIR) {
+        Ok(entries) => entries,
+        // No driver is installed.
+        Err(_) if names.is_none() => return Ok(0),
+        Err(e) => return Err(e).context("failed to list drivers"),
+    };
+    let mut files: Vec<String> = entries
+        .into_iter()
+        .filter(|x| !x.is_dir)
+        .map(|x| x.name)
+        .filter(|x| match names {
+            Some(names) => names.iter().any(|y| y.eq_ignore_ascii_case(x)),
+            None => x.to_ascii_lowercase().ends_with(".efi"),
+        })
+        .collect();
+    files.sort();
+    for i in names.unwrap_or_default() {
+        if !files.iter().any(|x| x.eq_ignore_ascii_case(i)) {
+            warn!("driver {} not found in {}.", i, DRIVER_DIR);
+        }
+    }
+    let mut started = 0;
+    for name in files {
+        let result = fs
+            .read(&path::join(DRIVER_DIR, &name))
+            .and_then(|data| start_driver(&data));
+        match result {
+            Ok(()) => {
+                info!("Started driver {}", name);
+                started += 1;
+            }
+            Err(e) => warn!("failed to start driver {}: {:#}", name, e),
+        }
+    }
+    if started > 0 {
+        connect_all()?;
+    }
+    Ok(started)
+}
@@ -25,3 +25,10 @@ impl<U> ToError<U> for Result<U, TryReserveError> {
         self.map_err(|x| anyhow!("failed to allocate, reason {}", x))
     }
 }
+
+#[cfg(target_arch = "x86_64")]
+impl<U> ToError<U> for Result<U, multiboot2::TooLarge> {
+    fn core_err(self) -> Result<U> {
+        self.map_err(|x| anyhow!("{}", x))
+    }
+}
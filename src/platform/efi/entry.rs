@@ -1,27 +1,42 @@
 //! Actual code is placed here.
 
 use core::arch::asm;
+use core::fmt::Write;
 
 //use crate::console::efi::EFIConsole;
-use crate::io::{LineEdit, ReadSecret, ReadString};
 use crate::platform::efi::console::EFIConsole;
-use crate::platform::efi::B2_UUID;
+use alloc::borrow::Cow;
 use alloc::boxed::Box;
+use alloc::format;
 use alloc::string::{String, ToString};
-use alloc::vec;
+use alloc::vec::Vec;
+use io::console::StartupAction;
+use io::{LineEdit, ReadSecret, ReadString};
 
 use anyhow::Context;
-use log::info;
+use log::{info, warn};
 use uefi::prelude::*;
 use uefi::proto::console::gop::{BltOp, BltPixel, GraphicsOutput};
 
 use crate::boot::boot::BootAble;
+use crate::config::boot_config::{
+    apply_parameter, load_selections, make_parameter, save_selections,
+};
+#[cfg(feature = "embedded-config")]
+use crate::config::embedded::{EmbeddedPolicy, EMBEDDED_POLICY};
+use crate::config::source;
+use crate::config::variables::expand_item;
+use crate::config::BootConfig;
 use crate::console::basicmenu::BasicMenu;
+use crate::console::browser::FileBrowser;
+use crate::console::dialog;
+use crate::console::shell::{Shell, ShellExit};
 use crate::platform::efi::boot::EFIBoot;
+use crate::platform::efi::driver::load_drivers;
 use crate::platform::efi::efi_error::ToError;
-use crate::platform::efi::file::EFIFile;
 use crate::platform::efi::logger::set_efi_var_logger;
-use config::Config;
+use crate::platform::efi::machine::apply_conditions;
+use config::{BootItem, BootTarget, Config};
 use uefi::Result;
 use uefi_services::println;
 
@@ -32,13 +47,17 @@ fn panic_handler(info: &core::panic::PanicInfo) -> ! {
     println!("panic: {}\n", info);
     println!("If you believe this is some sort of bug, report at github.com/pomoke/b2 .");
 
-    #[cfg(target_arch = "x86_64")]
-    unsafe {
-        // HLT is introduced since 8086. It's always OK to call this.
-        asm!("hlt", options(nomem, nostack));
-    };
-
-    loop {}
+    loop {
+        #[cfg(target_arch = "x86_64")]
+        unsafe {
+            // HLT is introduced since 8086. It's always OK to call this.
+            asm!("hlt", options(nomem, nostack));
+        };
+        #[cfg(target_arch = "aarch64")]
+        unsafe {
+            asm!("wfi", options(nomem, nostack));
+        };
+    }
 }
 
 fn draw(bs: &BootServices) -> Result {
@@ -77,18 +96,86 @@ fn draw(bs: &BootServices) -> Result {
     Ok(())
 }
 
-#[cfg(target_os = "uefi")]
-#[entry]
-pub fn main(image_handle: Handle, mut st: SystemTable<Boot>) -> Status {
-    use anyhow::anyhow;
-    use uefi::{guid, table::runtime::VariableVendor};
+/// Ask boot options of the item if it has any, then apply them to its cmdline.
+///
+/// Selections are saved as defaults of next boot.
+fn ask_options<'a>(
+    menu: &BasicMenu,
+    item: Cow<'a, BootItem>,
+    console: &mut EFIConsole,
+) -> anyhow::Result<Cow<'a, BootItem>> {
+    let Some(options) = item.options.as_deref() else {
+        return Ok(item);
+    };
+    let defaults = load_selections(&item, options);
+    let selections = menu.boot_config(options, defaults.as_deref(), console)?;
+    if let Err(e) = save_selections(&item, &selections) {
+        warn!("failed to save boot options: {}", e);
+    }
+    let parameters = make_parameter(options, &selections)?;
+    let mut item = item.into_owned();
+    apply_parameter(&mut item.target, &parameters)?;
+    Ok(Cow::Owned(item))
+}
 
-    use crate::{
-        config::{do_boot, BootConfig},
-        io::file::File,
+/// Load config, and show where it is from.
+///
+/// Returns `None` if no config is found.
+fn load_config(console: &mut EFIConsole) -> Option<BootConfig> {
+    let (config, report) = source::load_config();
+    for i in report.lines() {
+        let _ = writeln!(console, "Config: {}", i);
+    }
+    // A broken config would otherwise be replaced by fallback menu without notice.
+    if config.is_none() {
+        for (location, error) in &report.parse_errors {
+            let title = format!("Invalid config {}, using fallback menu.", location);
+            if let Err(e) = dialog::message(console, &title, &format!("{}", error)) {
+                warn!("failed to show config error: {}", e);
+            }
+        }
+    }
+    config.map(|mut x| {
+        apply_conditions(&mut x.0);
+        x
+    })
+}
+
+/// Count down `timeout` seconds before booting the default item.
+///
+/// Returns the path of item to boot, or `None` if a key is pressed to show menu.
+/// Pressing a hotkey boots its item instead.
+fn countdown(
+    config: &Config,
+    timeout: u32,
+    console: &mut EFIConsole,
+) -> anyhow::Result<Option<Vec<u32>>> {
+    let path = config.default_path();
+    let Some(default) = config.item(&path) else {
+        return Ok(None);
     };
+    for left in (1..=timeout).rev() {
+        write!(
+            console,
+            "\rBooting {} in {}s, press any key for menu. ",
+            default.name, left
+        )
+        .core_err()?;
+        if let Some(key) = console.wait_for_key_timeout(1000)? {
+            writeln!(console).core_err()?;
+            return Ok(BasicMenu::hotkey_path(config, &key));
+        }
+    }
+    if timeout > 0 {
+        writeln!(console).core_err()?;
+    }
+    Ok(Some(path))
+}
 
-    use config::{BootOption, BootOptionItem, BootOptionKind};
+#[cfg(target_os = "uefi")]
+#[entry]
+pub fn main(image_handle: Handle, mut st: SystemTable<Boot>) -> Status {
+    use crate::config::do_boot;
 
     uefi_services::init(&mut st).unwrap();
 
@@ -96,7 +183,6 @@ pub fn main(image_handle: Handle, mut st: SystemTable<Boot>) -> Status {
     set_efi_var_logger();
     let rev = st.uefi_revision();
     let bs = st.boot_services();
-    let rs = st.runtime_services();
 
     info!("FW Vendor {}", st.firmware_vendor());
     info!("FW Version {}", st.firmware_revision());
@@ -105,74 +191,88 @@ pub fn main(image_handle: Handle, mut st: SystemTable<Boot>) -> Status {
 
     //bs.set_watchdog_timer(0, 0, None).core_err().context("Failed to stop watchdog.").unwrap();
 
-    let mut config: BootConfig = File::<EFIFile>::open("b2.conf")
-        .and_then(|x| x.read_all())
-        .or_else(|_| {
-            rs.get_variable_boxed(cstr16!("Config"), &VariableVendor(B2_UUID))
-                .core_err()
-                .map(|x| x.0.into_vec())
-        })
-        .and_then(|x| {
-            serde_json_core::from_slice::<Config>(x.as_slice()).map_err(|x| anyhow!("{}", x))
-        })
-        .map(|x| BootConfig(x.0))
-        .inspect_err(|e| println!("error loading config: {:?}", e))
-        .unwrap_or(BootConfig::fallback_menu());
-
     let mut console = EFIConsole::from_system_table();
-    let boot_config = BootConfig::fallback_menu();
+    let config = load_config(&mut console).unwrap_or_else(BootConfig::fallback_menu);
+    // Drivers provide file systems firmware cannot read, where kernels may be found.
+    if let Err(e) = load_drivers(config.0.drivers.as_deref()) {
+        println!("error loading drivers: {:#}", e);
+    }
+
+    let (keys, held) = console.startup_keys();
+    let action = StartupAction::from_keys(&keys, held);
+    if action != StartupAction::Default {
+        info!("Startup action: {:?}", action);
+    }
+    // Neither a password nor an overriding embedded config is to be bypassed from keyboard.
+    let action = match action {
+        StartupAction::FallbackMenu if config.0.password.is_some() => StartupAction::ShowMenu,
+        #[cfg(feature = "embedded-config")]
+        StartupAction::FallbackMenu if EMBEDDED_POLICY == EmbeddedPolicy::Override => {
+            StartupAction::ShowMenu
+        }
+        k => k,
+    };
+    let mut boot_config = match action {
+        StartupAction::FallbackMenu => BootConfig::fallback_menu(),
+        _ => config,
+    };
     let mut buf = String::new();
     //console.edit_line(&mut buf, "test: ").unwrap();
 
-    // Test config menu.
-    let bootconf_test = [
-        BootOption {
-            identifier: String::from("NOACPI"),
-            pos: 0,
-            name: String::from("Disable ACPI"),
-            description: None,
-            option: BootOptionKind::Bool(String::from("acpi=no")),
-        },
-        BootOption {
-            identifier: String::from("ROOT"),
-            pos: 0,
-            name: String::from("Boot with"),
-            description: None,
-            option: BootOptionKind::Multiple(vec![
-                BootOptionItem {
-                    identifier: String::from("ROOT1"),
-                    name: String::from("debian"),
-                    description: None,
-                    value: String::from("root=/dev/sda1"),
-                },
-                BootOptionItem {
-                    identifier: String::from("ROOT2"),
-                    name: String::from("arch"),
-                    description: None,
-                    value: String::from("root=/dev/sda2"),
-                },
-                BootOptionItem {
-                    identifier: String::from("ROOT3"),
-                    name: String::from("nix"),
-                    description: None,
-                    value: String::from("root=/dev/sda3"),
-                },
-            ]),
-        },
-        BootOption {
-            identifier: String::from("TEST"),
-            pos: 0,
-            name: String::from("test"),
-            description: None,
-            option: BootOptionKind::Template(String::from("what=")),
-        },
-    ];
-
-    let menu = BasicMenu {};
-    //menu.boot_config(&bootconf_test, &mut console).unwrap();
+    let mut menu = BasicMenu::new();
+    // Without password, the default item is booted after timeout.
+    let mut selected = match (action, boot_config.0.timeout) {
+        (StartupAction::Default, Some(timeout)) if boot_config.0.password.is_none() => {
+            match keys.last() {
+                // Keys typed ahead stop the countdown, as any key during it does.
+                Some(key) => BasicMenu::hotkey_path(&boot_config.0, key),
+                None => countdown(&boot_config.0, timeout, &mut console).unwrap_or_else(|e| {
+                    println!("Failed to wait for timeout: {}", e);
+                    None
+                }),
+            }
+        }
+        _ => None,
+    };
 
     loop {
-        let option = menu.prompt(&boot_config.0, &mut console).unwrap();
+        // A submenu selected before menu is shown opens the menu there.
+        let open = selected.take().unwrap_or_default();
+        let option = match boot_config.0.item(&open) {
+            Some(item) if !matches!(item.target, BootTarget::Submenu { .. }) => Cow::Borrowed(item),
+            _ => menu.prompt(&boot_config.0, &open, &mut console).unwrap(),
+        };
+        if let BootTarget::Shell = option.target {
+            match Shell::new().run(&mut console) {
+                Ok(ShellExit::Menu) => {}
+                Ok(ShellExit::ReloadConfig) => match load_config(&mut console) {
+                    Some(config) => boot_config = config,
+                    None => println!("Keeping current config."),
+                },
+                Err(e) => println!("Shell failed: {}", e),
+            }
+            continue;
+        }
+        if let BootTarget::FileBrowser = option.target {
+            if let Err(e) = FileBrowser::new().and_then(|mut x| x.run(&mut console)) {
+                println!("File browser failed: {}", e);
+            }
+            continue;
+        }
+        let option = match ask_options(&menu, option, &mut console) {
+            Ok(option) => option,
+            Err(e) => {
+                println!("Failed to set boot options: {}", e);
+                continue;
+            }
+        };
+        let option = match expand_item(&boot_config.0, option) {
+            Ok(option) => option,
+            Err(e) => {
+                println!("Failed to boot: {}", e);
+                continue;
+            }
+        };
         println!("{:?}", option);
         let boot_result = do_boot(&option.target);
         match boot_result {
@@ -1,6 +1,6 @@
 //! UEFI File
 
-use alloc::{borrow::ToOwned, vec::Vec};
+use alloc::vec::Vec;
 use anyhow::{anyhow, Context, Result};
 use uefi::{
     cstr16,
@@ -12,6 +12,9 @@ use uefi_services::system_table;
 use crate::platform::ToError;
 
 use crate::io::file::File;
+use crate::platform::efi::fs::EfiFileSystem;
+use io::file::DirEntry;
+use io::fs::FileSystem;
 
 pub struct EFIFile {
     file: FileHandle,
@@ -61,3 +64,10 @@ impl File<EFIFile> {
         Ok(buf)
     }
 }
+
+/// List a directory on the volume b2 was loaded from.
+///
+/// Both `/` and `\` are accepted as separator.
+pub fn list_dir(path: &str) -> Result<Vec<DirEntry>> {
+    EfiFileSystem::current()?.read_dir(path)
+}
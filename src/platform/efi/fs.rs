@@ -0,0 +1,259 @@
+//! File systems and volumes of firmware.
+
+use alloc::{boxed::Box, string::String, vec::Vec};
+use anyhow::{anyhow, Context, Result};
+use config::ImageLocation;
+use uefi::{
+    proto::{
+        device_path::{media::PartitionSignature, DevicePath, DevicePathNodeEnum},
+        media::{
+            file::{
+                Directory as BaseDirectory, File as BaseFile, FileAttribute, FileInfo, FileMode,
+                FileSystemVolumeLabel, RegularFile as BaseRegularFile,
+            },
+            fs::SimpleFileSystem,
+        },
+    },
+    CString16, Handle,
+};
+use uefi_services::system_table;
+
+use crate::platform::efi::block::{device_path_text, get_protocol, list_blocks};
+use crate::platform::ToError;
+use io::file::DirEntry;
+use io::fs::{
+    path, Directory, FileSystem, Metadata, RegularFile, VolumeAddress, VolumeInfo, VolumePath,
+    Volumes,
+};
+
+pub struct EfiRegularFile {
+    file: BaseRegularFile,
+    size: u64,
+}
+
+impl RegularFile for EfiRegularFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.file.read(buf).core_err()
+    }
+
+    fn seek(&mut self, pos: u64) -> Result<()> {
+        // Firmware refuses positions after end of file, other than end of file itself.
+        self.file.set_position(pos.min(self.size)).core_err()
+    }
+
+    fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+pub struct EfiDirectory {
+    dir: BaseDirectory,
+}
+
+impl Directory for EfiDirectory {
+    fn entries(&mut self) -> Result<Vec<DirEntry>> {
+        self.dir.reset_entry_readout().core_err()?;
+        let mut ret = Vec::new();
+        while let Some(info) = self.dir.read_entry_boxed().core_err()? {
+            let name = String::from(info.file_name());
+            if name == "." || name == ".." {
+                continue;
+            }
+            ret.push(DirEntry {
+                name,
+                is_dir: info.is_directory(),
+                size: info.file_size(),
+            });
+        }
+        Ok(ret)
+    }
+}
+
+/// A volume with Simple File System protocol.
+pub struct EfiFileSystem {
+    root: BaseDirectory,
+}
+
+impl EfiFileSystem {
+    /// Mount the volume of `device`.
+    pub fn mount(device: Handle) -> Result<Self> {
+        let st = system_table();
+        let bs = st.boot_services();
+        let mut fs = get_protocol::<SimpleFileSystem>(bs, device)?;
+        let root = fs.open_volume().core_err()?;
+        Ok(Self { root })
+    }
+
+    /// Mount the volume b2 was loaded from.
+    pub fn current() -> Result<Self> {
+        Self::mount(current_device()?)
+    }
+
+    fn open_handle(&mut self, path: &str) -> Result<uefi::proto::media::file::FileHandle> {
+        let path = path::normalize(path);
+        let efi_path =
+            CString16::try_from(path.as_str()).map_err(|_| anyhow!("Failed to convert!"))?;
+        self.root
+            .open(&efi_path, FileMode::Read, FileAttribute::READ_ONLY)
+            .core_err()
+            .with_context(|| alloc::format!("Failed to open {}", path))
+    }
+}
+
+impl FileSystem for EfiFileSystem {
+    fn open(&mut self, path: &str) -> Result<Box<dyn RegularFile>> {
+        let mut file = self
+            .open_handle(path)?
+            .into_regular_file()
+            .ok_or_else(|| anyhow!("{} is not a file.", path))?;
+        let size = file.get_boxed_info::<FileInfo>().core_err()?.file_size();
+        Ok(Box::new(EfiRegularFile { file, size }))
+    }
+
+    fn open_dir(&mut self, path: &str) -> Result<Box<dyn Directory>> {
+        let dir = self
+            .open_handle(path)?
+            .into_directory()
+            .ok_or_else(|| anyhow!("{} is not a directory.", path))?;
+        Ok(Box::new(EfiDirectory { dir }))
+    }
+
+    fn stat(&mut self, path: &str) -> Result<Metadata> {
+        let info = self
+            .open_handle(path)?
+            .get_boxed_info::<FileInfo>()
+            .core_err()?;
+        Ok(Metadata {
+            is_dir: info.is_directory(),
+            size: if info.is_directory() {
+                0
+            } else {
+                info.file_size()
+            },
+        })
+    }
+
+    fn label(&mut self) -> Result<String> {
+        let label = self
+            .root
+            .get_boxed_info::<FileSystemVolumeLabel>()
+            .core_err()?;
+        Ok(String::from(label.volume_label()))
+    }
+}
+
+/// Device b2 was loaded from.
+pub fn current_device() -> Result<Handle> {
+    let st = system_table();
+    let bs = st.boot_services();
+    let image = get_protocol::<uefi::proto::loaded_image::LoadedImage>(bs, bs.image_handle())?;
+    image
+        .device()
+        .ok_or_else(|| anyhow!("b2 is not loaded from a device."))
+}
+
+/// GUID of the GPT partition `handle` is on, from its device path.
+pub fn partition_guid(handle: Handle) -> Option<String> {
+    let st = system_table();
+    let bs = st.boot_services();
+    let path = get_protocol::<DevicePath>(bs, handle).ok()?;
+    path.node_iter().find_map(|node| match node.as_enum() {
+        Ok(DevicePathNodeEnum::MediaHardDrive(hd)) => match hd.partition_signature() {
+            PartitionSignature::Guid(guid) => Some(alloc::format!("{}", guid)),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+/// Volumes with Simple File System protocol.
+///
+/// Volumes are listed once on creation, so indexes stay the same while it lives.
+pub struct EfiVolumes {
+    handles: Vec<Handle>,
+}
+
+impl EfiVolumes {
+    pub fn new() -> Result<Self> {
+        let st = system_table();
+        let bs = st.boot_services();
+        let handles = bs.find_handles::<SimpleFileSystem>().core_err()?;
+        Ok(Self { handles })
+    }
+
+    /// Handle of volume with `index`.
+    pub fn handle(&self, index: usize) -> Result<Handle> {
+        self.handles
+            .get(index)
+            .copied()
+            .ok_or_else(|| anyhow!("no volume #{}.", index))
+    }
+}
+
+impl Volumes for EfiVolumes {
+    fn volumes(&mut self) -> Result<Vec<VolumeInfo>> {
+        let current = current_device().ok();
+        Ok(self
+            .handles
+            .iter()
+            .enumerate()
+            .map(|(index, &handle)| VolumeInfo {
+                index,
+                label: EfiFileSystem::mount(handle)
+                    .and_then(|mut x| x.label())
+                    .unwrap_or_default(),
+                partition_guid: partition_guid(handle),
+                device_path: device_path_text(handle).unwrap_or_default(),
+                current: current == Some(handle),
+            })
+            .collect())
+    }
+
+    fn mount(&mut self, index: usize) -> Result<Box<dyn FileSystem>> {
+        Ok(Box::new(EfiFileSystem::mount(self.handle(index)?)?))
+    }
+}
+
+/// Find the device of a volume with Simple File System protocol.
+///
+/// If only a block device matches, there is no driver for its file system.
+pub fn resolve_volume(address: &VolumeAddress) -> Result<Handle> {
+    if *address == VolumeAddress::Current {
+        return current_device();
+    }
+    let mut volumes = EfiVolumes::new()?;
+    let error = match volumes.find(address) {
+        Ok(volume) => return volumes.handle(volume.index),
+        Err(e) => e,
+    };
+    let block = list_blocks()?.into_iter().find(|x| match address {
+        VolumeAddress::PartUuid(guid) => partition_guid(x.handle).as_ref() == Some(guid),
+        VolumeAddress::DevicePath(path) => x.path.eq_ignore_ascii_case(path),
+        _ => false,
+    });
+    match block {
+        Some(block) => Err(anyhow!(
+            "{} has no file system firmware can read, a driver may be missing.",
+            block.path
+        )),
+        None => Err(error),
+    }
+}
+
+/// Mount the volume of a path such as `PARTUUID=...:/vmlinuz`, and return it with the path on it.
+pub fn mount_path(location: &str) -> Result<(EfiFileSystem, String)> {
+    let location = VolumePath::parse(location)?;
+    let fs = EfiFileSystem::mount(resolve_volume(&location.volume)?)?;
+    Ok((fs, location.path))
+}
+
+/// Read a whole image, such as a kernel or its modules.
+pub fn read_image(image: &ImageLocation) -> Result<Vec<u8>> {
+    match image {
+        ImageLocation::Path { path } => {
+            let (mut fs, path) = mount_path(path)?;
+            fs.read(&path)
+        }
+        _ => Err(anyhow!("only images at a path can be read for now.")),
+    }
+}
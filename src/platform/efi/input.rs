@@ -0,0 +1,92 @@
+//! Simple Text Input Ex protocol.
+//!
+//! Unlike Simple Text Input, this protocol reports the state of modifier keys.
+//! `uefi` does not wrap it yet.
+
+use core::ffi::c_void;
+use core::mem::MaybeUninit;
+
+use uefi::proto::unsafe_protocol;
+use uefi::{Event, Status, StatusExt};
+use uefi_raw::protocol::console::InputKey;
+
+pub const SHIFT_STATE_VALID: u32 = 0x8000_0000;
+pub const RIGHT_SHIFT_PRESSED: u32 = 0x0000_0001;
+pub const LEFT_SHIFT_PRESSED: u32 = 0x0000_0002;
+pub const RIGHT_CONTROL_PRESSED: u32 = 0x0000_0004;
+pub const LEFT_CONTROL_PRESSED: u32 = 0x0000_0008;
+pub const RIGHT_ALT_PRESSED: u32 = 0x0000_0010;
+pub const LEFT_ALT_PRESSED: u32 = 0x0000_0020;
+
+pub const TOGGLE_STATE_VALID: u8 = 0x80;
+pub const KEY_STATE_EXPOSED: u8 = 0x40;
+
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct KeyState {
+    pub key_shift_state: u32,
+    pub key_toggle_state: u8,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct KeyData {
+    pub key: InputKey,
+    pub key_state: KeyState,
+}
+
+#[derive(Debug)]
+#[repr(C)]
+#[unsafe_protocol("dd9e7534-7762-4698-8c14-f58517a625aa")]
+pub struct InputEx {
+    reset: unsafe extern "efiapi" fn(this: *mut Self, extended_verification: bool) -> Status,
+    read_key_stroke_ex:
+        unsafe extern "efiapi" fn(this: *mut Self, key_data: *mut KeyData) -> Status,
+    wait_for_key_ex: *mut c_void,
+    set_state: unsafe extern "efiapi" fn(this: *mut Self, key_toggle_state: *const u8) -> Status,
+    // Key notification is not used.
+    register_key_notify: *const c_void,
+    unregister_key_notify: *const c_void,
+}
+
+impl InputEx {
+    /// Reads the next keystroke with modifier state, if any.
+    pub fn read_key(&mut self) -> uefi::Result<Option<KeyData>> {
+        let mut key = MaybeUninit::<KeyData>::uninit();
+        match unsafe { (self.read_key_stroke_ex)(self, key.as_mut_ptr()) } {
+            Status::NOT_READY => Ok(None),
+            other => other.to_result_with_val(|| Some(unsafe { key.assume_init() })),
+        }
+    }
+
+    /// Reads the next keystroke, or modifier state alone if no key is pressed.
+    ///
+    /// Modifiers held alone are only reported if firmware supports exposing partial keystrokes.
+    /// Otherwise, key and state are all zero.
+    pub fn read_key_state(&mut self) -> uefi::Result<KeyData> {
+        self.expose_partial(true);
+        let mut key = MaybeUninit::<KeyData>::zeroed();
+        let status = unsafe { (self.read_key_stroke_ex)(self, key.as_mut_ptr()) };
+        self.expose_partial(false);
+        match status {
+            Status::SUCCESS | Status::NOT_READY => Ok(unsafe { key.assume_init() }),
+            other => Err(other.into()),
+        }
+    }
+
+    /// Report modifiers pressed alone as keystrokes.
+    fn expose_partial(&mut self, expose: bool) {
+        let state = if expose {
+            TOGGLE_STATE_VALID | KEY_STATE_EXPOSED
+        } else {
+            TOGGLE_STATE_VALID
+        };
+        // Unsupported by some firmware, which is fine as partial keystrokes are just not reported.
+        let _ = unsafe { (self.set_state)(self, &state) };
+    }
+
+    /// Event to wait for a keystroke.
+    pub fn wait_for_key_event(&self) -> Option<Event> {
+        unsafe { Event::from_ptr(self.wait_for_key_ex) }
+    }
+}
@@ -0,0 +1,321 @@
+//! Linux boot on EFI, through the EFI stub of kernel.
+//!
+//! Initrd is given by the LoadFile2 protocol on the device path Linux looks for since 5.8, so it
+//! may be on any volume. A device tree is installed as configuration table, after firmware such
+//! as U-Boot applies its fixups to it.
+
+use alloc::{boxed::Box, format, vec::Vec};
+use anyhow::{anyhow, Context, Result};
+use config::ImageLocation;
+use core::ffi::c_void;
+use core::ptr::addr_of;
+use log::info;
+use uefi::proto::device_path::DevicePath;
+use uefi::proto::loaded_image::LoadedImage;
+use uefi::proto::unsafe_protocol;
+use uefi::table::boot::{AllocateType, BootServices, LoadImageSource, MemoryType};
+use uefi::{guid, CString16, Guid, Handle, Identify, Status};
+use uefi_services::system_table;
+
+use crate::boot::linux::check_image;
+use crate::platform::efi::block::get_protocol;
+use crate::platform::efi::fs::read_image;
+use crate::platform::ToError;
+
+/// Vendor media device path Linux loads initrd from.
+const INITRD_MEDIA_GUID: Guid = guid!("5568e427-68fc-4f3d-ac74-ca555231cc68");
+const LOAD_FILE2_GUID: Guid = guid!("4006c0c1-fcb3-403e-996d-4a6c8724e06d");
+/// Configuration table of flattened device tree.
+const DTB_TABLE_GUID: Guid = guid!("b1b621d5-f19c-41a5-830b-d9152c69aae0");
+/// Magic of flattened device tree, in big endian.
+const FDT_MAGIC: u32 = 0xd00d_feed;
+/// Room after device tree for fixups of firmware.
+const FDT_ROOM: usize = 0x10000;
+const PAGE_SIZE: usize = 4096;
+
+/// Device path of initrd: a vendor media node, then the end.
+#[repr(C, packed)]
+struct InitrdDevicePath {
+    ty: u8,
+    sub_type: u8,
+    length: [u8; 2],
+    guid: Guid,
+    end: [u8; 4],
+}
+
+static INITRD_DEVICE_PATH: InitrdDevicePath = InitrdDevicePath {
+    ty: 0x04,
+    sub_type: 0x03,
+    length: [20, 0],
+    guid: INITRD_MEDIA_GUID,
+    end: [0x7f, 0xff, 4, 0],
+};
+
+/// LoadFile2 protocol giving initrd.
+#[repr(C)]
+struct InitrdLoader {
+    load_file: unsafe extern "efiapi" fn(
+        this: *mut InitrdLoader,
+        file_path: *const c_void,
+        boot_policy: u8,
+        buffer_size: *mut usize,
+        buffer: *mut c_void,
+    ) -> Status,
+    initrd: Vec<u8>,
+}
+
+unsafe extern "efiapi" fn load_initrd(
+    this: *mut InitrdLoader,
+    _file_path: *const c_void,
+    boot_policy: u8,
+    buffer_size: *mut usize,
+    buffer: *mut c_void,
+) -> Status {
+    if this.is_null() || buffer_size.is_null() {
+        return Status::INVALID_PARAMETER;
+    }
+    // Initrd is not a boot option.
+    if boot_policy != 0 {
+        return Status::UNSUPPORTED;
+    }
+    let initrd = &(*this).initrd;
+    if buffer.is_null() || *buffer_size < initrd.len() {
+        *buffer_size = initrd.len();
+        return Status::BUFFER_TOO_SMALL;
+    }
+    core::ptr::copy_nonoverlapping(initrd.as_ptr(), buffer.cast::<u8>(), initrd.len());
+    *buffer_size = initrd.len();
+    Status::SUCCESS
+}
+
+/// Initrd installed on a handle of its own.
+struct Initrd {
+    handle: Handle,
+    loader: *mut InitrdLoader,
+}
+
+impl Initrd {
+    fn install(bs: &BootServices, initrd: Vec<u8>) -> Result<Self> {
+        let path = addr_of!(INITRD_DEVICE_PATH).cast::<c_void>();
+        unsafe {
+            let handle = bs
+                .install_protocol_interface(None, &DevicePath::GUID, path)
+                .core_err()
+                .context("failed to install device path of initrd")?;
+            let loader = Box::into_raw(Box::new(InitrdLoader {
+                load_file: load_initrd,
+                initrd,
+            }));
+            // Nothing is left installed if the loader is not.
+            if let Err(e) =
+                bs.install_protocol_interface(Some(handle), &LOAD_FILE2_GUID, loader.cast())
+            {
+                let _ = bs.uninstall_protocol_interface(handle, &DevicePath::GUID, path);
+                drop(Box::from_raw(loader));
+                return Err(e).core_err().context("failed to install initrd");
+            }
+            Ok(Self { handle, loader })
+        }
+    }
+
+    /// Take initrd back, after the kernel returned.
+    fn uninstall(self, bs: &BootServices) {
+        let path = addr_of!(INITRD_DEVICE_PATH).cast::<c_void>();
+        unsafe {
+            let loaded =
+                bs.uninstall_protocol_interface(self.handle, &LOAD_FILE2_GUID, self.loader.cast());
+            let _ = bs.uninstall_protocol_interface(self.handle, &DevicePath::GUID, path);
+            // The loader is leaked if firmware still holds it.
+            if loaded.is_ok() {
+                drop(Box::from_raw(self.loader));
+            }
+        }
+    }
+}
+
+/// Fixup protocol of firmware such as U-Boot, which fills in the device tree of the board.
+#[repr(C)]
+#[unsafe_protocol("e617d64c-fe08-46da-f4dc-bbd5870c7300")]
+struct DtFixup {
+    revision: u64,
+    fixup: unsafe extern "efiapi" fn(
+        this: *mut DtFixup,
+        fdt: *mut c_void,
+        buffer_size: *mut usize,
+        flags: u32,
+    ) -> Status,
+}
+
+/// Apply fixups, and reserve memory as the device tree asks.
+const DT_APPLY_FIXUPS: u32 = 0x1;
+const DT_RESERVE_MEMORY: u32 = 0x2;
+
+/// Copy device tree to pages of `size`, which Linux keeps as ACPI reclaimable memory.
+fn allocate_fdt(bs: &BootServices, data: &[u8], size: usize) -> Result<*mut u8> {
+    let addr = bs
+        .allocate_pages(
+            AllocateType::AnyPages,
+            MemoryType::ACPI_RECLAIM,
+            size.div_ceil(PAGE_SIZE),
+        )
+        .core_err()?;
+    let fdt = addr as *mut u8;
+    unsafe {
+        core::ptr::copy_nonoverlapping(data.as_ptr(), fdt, data.len());
+        core::ptr::write_bytes(fdt.add(data.len()), 0, size - data.len());
+    }
+    Ok(fdt)
+}
+
+/// Apply fixups of firmware to the device tree at `fdt`, if it has them.
+///
+/// The device tree may be moved to larger pages. Returns where it is and the size of its pages,
+/// which are freed on error.
+fn fixup_fdt(
+    bs: &BootServices,
+    data: &[u8],
+    mut fdt: *mut u8,
+    mut size: usize,
+) -> Result<(*mut u8, usize)> {
+    let Ok(handle) = bs.get_handle_for_protocol::<DtFixup>() else {
+        return Ok((fdt, size));
+    };
+    let free = |fdt: *mut u8, size: usize| {
+        let _ = unsafe { bs.free_pages(fdt as u64, size.div_ceil(PAGE_SIZE)) };
+    };
+    let mut protocol = match get_protocol::<DtFixup>(bs, handle) {
+        Ok(protocol) => protocol,
+        Err(e) => {
+            free(fdt, size);
+            return Err(e);
+        }
+    };
+    let this: *mut DtFixup = &mut *protocol;
+    let flags = DT_APPLY_FIXUPS | DT_RESERVE_MEMORY;
+    let mut allocated = size;
+    let mut status = unsafe { (protocol.fixup)(this, fdt.cast(), &mut size, flags) };
+    if status == Status::BUFFER_TOO_SMALL {
+        // Firmware tells the size it needs, and the device tree is copied again to that size.
+        // The size is not trusted to hold the device tree itself.
+        free(fdt, allocated);
+        allocated = size.max(data.len() + FDT_ROOM);
+        fdt = allocate_fdt(bs, data, allocated)?;
+        size = allocated;
+        status = unsafe { (protocol.fixup)(this, fdt.cast(), &mut size, flags) };
+    }
+    if status.is_error() {
+        free(fdt, allocated);
+        return Err(anyhow!(
+            "firmware failed to fix up device tree, status {}",
+            status
+        ));
+    }
+    Ok((fdt, allocated))
+}
+
+/// Device tree installed in place of the one of firmware, which is put back on drop.
+struct Devicetree<'a> {
+    bs: &'a BootServices,
+    fdt: *mut u8,
+    size: usize,
+    /// Device tree of firmware, or null if it has none.
+    firmware: *const c_void,
+}
+
+impl Drop for Devicetree<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = self
+                .bs
+                .install_configuration_table(&DTB_TABLE_GUID, self.firmware);
+            let _ = self
+                .bs
+                .free_pages(self.fdt as u64, self.size.div_ceil(PAGE_SIZE));
+        }
+    }
+}
+
+/// Load device tree, and install it for the kernel in place of the one of firmware.
+fn install_devicetree<'a>(
+    bs: &'a BootServices,
+    location: &ImageLocation,
+) -> Result<Devicetree<'a>> {
+    let data = read_image(location).context("failed to read device tree")?;
+    let magic = data
+        .get(..4)
+        .map(|x| u32::from_be_bytes([x[0], x[1], x[2], x[3]]));
+    if magic != Some(FDT_MAGIC) {
+        return Err(anyhow!("device tree is not a flattened device tree blob."));
+    }
+    let firmware = system_table()
+        .config_table()
+        .iter()
+        .find(|x| x.guid == DTB_TABLE_GUID)
+        .map_or(core::ptr::null(), |x| x.address);
+    let size = data.len() + FDT_ROOM;
+    let fdt = allocate_fdt(bs, &data, size)?;
+    let (fdt, size) = fixup_fdt(bs, &data, fdt, size)?;
+    let devicetree = Devicetree {
+        bs,
+        fdt,
+        size,
+        firmware,
+    };
+    unsafe { bs.install_configuration_table(&DTB_TABLE_GUID, fdt.cast()) }
+        .core_err()
+        .context("failed to install device tree")?;
+    Ok(devicetree)
+}
+
+/// Load kernel from `image`, and start it with `cmdline`.
+fn start(bs: &BootServices, image: &[u8], cmdline: &str) -> Result<()> {
+    let handle = bs
+        .load_image(
+            bs.image_handle(),
+            LoadImageSource::FromBuffer {
+                buffer: image,
+                file_path: None,
+            },
+        )
+        .core_err()
+        .context("failed to load kernel")?;
+    let cmdline = CString16::try_from(cmdline).map_err(|_| anyhow!("failed to convert cmdline"))?;
+    {
+        let mut loaded = bs
+            .open_protocol_exclusive::<LoadedImage>(handle)
+            .core_err()?;
+        unsafe { loaded.set_load_options(cmdline.as_ptr().cast(), cmdline.num_bytes() as u32) };
+    }
+    info!("Booting Linux, cmdline {}", cmdline);
+    bs.start_image(handle).core_err()
+}
+
+/// Boot Linux `kernel` through its EFI stub, with initrds concatenated as one.
+pub fn boot(
+    kernel: &ImageLocation,
+    initrd: &[ImageLocation],
+    cmdline: &str,
+    devicetree: Option<&ImageLocation>,
+) -> Result<!> {
+    let st = system_table();
+    let bs = st.boot_services();
+    let image = read_image(kernel).context("failed to read kernel")?;
+    check_image(&image)?;
+    let mut data = Vec::new();
+    for i in initrd {
+        data.extend(read_image(i).with_context(|| format!("failed to read initrd {:?}", i))?);
+    }
+
+    // Device tree of firmware is put back if the kernel returns, or initrd fails to install.
+    let _devicetree = devicetree.map(|x| install_devicetree(bs, x)).transpose()?;
+    let initrd = match data.is_empty() {
+        true => None,
+        false => Some(Initrd::install(bs, data)?),
+    };
+    let result = start(bs, &image, cmdline);
+    if let Some(initrd) = initrd {
+        initrd.uninstall(bs);
+    }
+    result?;
+    Err(anyhow!("kernel returned."))
+}
@@ -0,0 +1,179 @@
+//! Facts about the machine, for conditions of menu entries.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use config::condition::Machine;
+use config::{BootItem, Config};
+use log::{info, warn};
+use uefi::table::cfg::{SMBIOS3_GUID, SMBIOS_GUID};
+use uefi::table::runtime::VariableVendor;
+use uefi::Guid;
+use uefi_services::system_table;
+
+use crate::platform::efi::fs::mount_path;
+use crate::platform::efi::var::get_vendor_var;
+use crate::platform::efi::B2_VENDOR;
+use io::fs::FileSystem;
+
+/// Type of SMBIOS structure of system information.
+const SMBIOS_SYSTEM_INFO: u8 = 1;
+/// Type of SMBIOS structure ending the table.
+const SMBIOS_END: u8 = 127;
+
+/// SMBIOS structure table, from SMBIOS 3 entry point if there is one.
+fn smbios_table() -> Option<&'static [u8]> {
+    let st = system_table();
+    let entries = st.config_table();
+    let find = |guid: Guid| {
+        entries
+            .iter()
+            .find(|x| x.guid == guid)
+            .map(|x| x.address as *const u8)
+    };
+    // SAFETY: Entry points are placed by firmware, and checked against their anchors before use.
+    // The table they point to stays in memory while firmware runs.
+    unsafe {
+        if let Some(entry) = find(SMBIOS3_GUID) {
+            if core::slice::from_raw_parts(entry, 5) == b"_SM3_" {
+                let size = (entry.add(0x0c) as *const u32).read_unaligned();
+                let address = (entry.add(0x10) as *const u64).read_unaligned();
+                return Some(core::slice::from_raw_parts(
+                    address as *const u8,
+                    size as usize,
+                ));
+            }
+        }
+        let entry = find(SMBIOS_GUID)?;
+        if core::slice::from_raw_parts(entry, 4) != b"_SM_" {
+            return None;
+        }
+        let size = (entry.add(0x16) as *const u16).read_unaligned();
+        let address = (entry.add(0x18) as *const u32).read_unaligned();
+        Some(core::slice::from_raw_parts(
+            address as *const u8,
+            size as usize,
+        ))
+    }
+}
+
+/// System information in SMBIOS. Fields not given are empty.
+pub struct SystemInfo {
+    pub vendor: String,
+    pub product: String,
+    pub serial: String,
+}
+
+/// Find system information structure in SMBIOS table.
+fn parse_system_info(table: &[u8]) -> Option<SystemInfo> {
+    let mut pos = 0;
+    while pos + 4 <= table.len() {
+        let kind = table[pos];
+        let length = table[pos + 1] as usize;
+        let strings = pos + length;
+        if length < 4 || strings > table.len() {
+            return None;
+        }
+        // Strings follow the formatted part, and end with two NULs.
+        let end = (strings..table.len().saturating_sub(1))
+            .find(|&x| table[x] == 0 && table[x + 1] == 0)?;
+        if kind == SMBIOS_SYSTEM_INFO {
+            let texts: Vec<&[u8]> = table[strings..end].split(|&x| x == 0).collect();
+            // Fields hold string numbers, counting from 1, where 0 is no string.
+            let field = |offset: usize| {
+                (offset < length)
+                    .then(|| table[pos + offset])
+                    .and_then(|x| (x as usize).checked_sub(1))
+                    .and_then(|x| texts.get(x))
+                    .map(|x| String::from_utf8_lossy(x).trim().into())
+                    .unwrap_or_default()
+            };
+            return Some(SystemInfo {
+                vendor: field(4),
+                product: field(5),
+                serial: field(7),
+            });
+        }
+        if kind == SMBIOS_END {
+            return None;
+        }
+        pos = end + 2;
+    }
+    None
+}
+
+/// System information of this machine, if firmware provides SMBIOS.
+pub fn system_info() -> Option<SystemInfo> {
+    parse_system_info(smbios_table()?)
+}
+
+/// The machine b2 runs on.
+pub struct EfiMachine;
+
+impl Machine for EfiMachine {
+    fn arch(&self) -> &str {
+        if cfg!(target_arch = "x86_64") {
+            "x86_64"
+        } else if cfg!(target_arch = "aarch64") {
+            "aarch64"
+        } else if cfg!(target_arch = "x86") {
+            "x86"
+        } else {
+            "unknown"
+        }
+    }
+
+    fn file_exists(&mut self, path: &str) -> bool {
+        mount_path(path)
+            .and_then(|(mut fs, path)| fs.stat(&path))
+            .is_ok()
+    }
+
+    fn secure_boot(&mut self) -> Option<bool> {
+        self.variable("SecureBoot", Some("global"))
+            .and_then(|x| x.first().map(|&x| x == 1))
+    }
+
+    fn setup_mode(&mut self) -> Option<bool> {
+        self.variable("SetupMode", Some("global"))
+            .and_then(|x| x.first().map(|&x| x == 1))
+    }
+
+    fn smbios(&mut self) -> Option<(String, String)> {
+        system_info().map(|x| (x.vendor, x.product))
+    }
+
+    fn variable(&mut self, name: &str, vendor: Option<&str>) -> Option<Vec<u8>> {
+        let vendor = match vendor {
+            None => B2_VENDOR,
+            Some(x) if x.eq_ignore_ascii_case("global") => VariableVendor::GLOBAL_VARIABLE,
+            Some(x) => match Guid::try_parse(x) {
+                Ok(guid) => VariableVendor(guid),
+                Err(_) => {
+                    warn!("invalid vendor GUID {} of variable {}.", x, name);
+                    return None;
+                }
+            },
+        };
+        get_vendor_var(name, &vendor).ok()
+    }
+
+    fn firmware_vendor(&mut self) -> Option<String> {
+        Some(String::from(system_table().firmware_vendor()))
+    }
+}
+
+/// Hide entries of `config` whose conditions do not hold on this machine.
+pub fn apply_conditions(config: &mut Config) {
+    let mut machine = EfiMachine;
+    config.retain_items(&mut |item: &BootItem| {
+        let shown = item
+            .conditions
+            .iter()
+            .flatten()
+            .all(|x| x.holds(&mut machine));
+        if !shown {
+            info!("hiding entry {}, its conditions do not hold.", item.name);
+        }
+        shown
+    });
+}
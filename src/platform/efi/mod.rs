@@ -1,14 +1,23 @@
 use uefi::{guid, table::runtime::VariableVendor, Guid};
 
+pub mod block;
 pub mod boot;
 pub mod console;
+pub mod driver;
 pub mod efi_error;
 pub mod entry;
 pub mod error;
 pub mod file;
+pub mod fs;
 pub mod init;
+pub mod input;
+pub mod linux;
 pub mod logger;
+pub mod machine;
+#[cfg(target_arch = "x86_64")]
+pub mod multiboot2;
 pub mod tty;
+pub mod var;
 
 pub const B2_UUID: Guid = guid!("95f342d7-c48a-4799-8df5-6710597a7430");
 pub const B2_VENDOR: VariableVendor = VariableVendor(B2_UUID);
@@ -0,0 +1,294 @@
+//! Multiboot2 kernel boot on EFI.
+//!
+//! The kernel is loaded where it is linked, modules and boot information to pages below 4 GiB.
+//! Kernels with the EFI boot services and EFI amd64 entry tags are entered in 64-bit mode with
+//! boot services running, and others in 32-bit protected mode after boot services are exited.
+
+use alloc::{format, vec, vec::Vec};
+use anyhow::{anyhow, Context, Result};
+use config::{ImageLocation, Module};
+use core::mem::size_of;
+use log::info;
+use multiboot2::{memory, tag, Header, InfoWriter, Segment, TooLarge};
+use uefi::proto::console::gop::{GraphicsOutput, PixelFormat};
+use uefi::table::boot::{AllocateType, BootServices, MemoryDescriptor, MemoryMap, MemoryType};
+use uefi::table::cfg::{ACPI2_GUID, ACPI_GUID};
+use uefi::table::{Boot, SystemTable};
+use uefi_services::system_table;
+
+use crate::boot::multiboot2::{enter_amd64, enter_i386, halt};
+use crate::platform::efi::block::get_protocol;
+use crate::platform::efi::fs::read_image;
+use crate::platform::ToError;
+
+const PAGE_SIZE: u64 = 4096;
+/// Room of boot information for tags other than cmdlines and memory maps.
+const INFO_RESERVE: usize = 4096;
+/// Entries of memory map more than firmware reports, as it grows until boot services exit.
+const MMAP_SLACK: usize = 32;
+/// Size of an entry in both memory map tags.
+const MMAP_ENTRY: usize = 24 + size_of::<MemoryDescriptor>();
+
+/// Allocate pages below 4 GiB for `size` bytes.
+fn allocate_low(bs: &BootServices, size: usize) -> Result<&'static mut [u8]> {
+    let pages = (size as u64).div_ceil(PAGE_SIZE).max(1) as usize;
+    let addr = bs
+        .allocate_pages(
+            AllocateType::MaxAddress(u32::MAX.into()),
+            MemoryType::LOADER_DATA,
+            pages,
+        )
+        .core_err()
+        .context("failed to allocate memory below 4 GiB")?;
+    Ok(unsafe { core::slice::from_raw_parts_mut(addr as *mut u8, size) })
+}
+
+/// Load segments of kernel to where they are linked.
+fn load_kernel(bs: &BootServices, image: &[u8], segments: &[Segment]) -> Result<()> {
+    // Pages of segments, merged as segments may share a page.
+    let mut ranges: Vec<(u64, u64)> = segments
+        .iter()
+        .filter(|x| x.size > 0)
+        .map(|x| {
+            let end = x
+                .end()?
+                .checked_next_multiple_of(PAGE_SIZE)
+                .ok_or_else(|| anyhow!("segment at {:#x} ends out of memory.", x.addr))?;
+            Ok((x.addr & !(PAGE_SIZE - 1), end))
+        })
+        .collect::<Result<_>>()?;
+    ranges.sort_unstable();
+    let mut merged: Vec<(u64, u64)> = Vec::new();
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    for (start, end) in merged {
+        bs.allocate_pages(
+            AllocateType::Address(start),
+            MemoryType::LOADER_CODE,
+            ((end - start) / PAGE_SIZE) as usize,
+        )
+        .core_err()
+        .with_context(|| format!("memory at {:#x} for kernel is in use", start))?;
+    }
+    for i in segments {
+        let dest = unsafe { core::slice::from_raw_parts_mut(i.addr as *mut u8, i.size as usize) };
+        let (data, bss) = dest.split_at_mut(i.data.len());
+        data.copy_from_slice(&image[i.data.clone()]);
+        bss.fill(0);
+    }
+    Ok(())
+}
+
+/// Write framebuffer of GOP, switched to the resolution the kernel prefers if there is one.
+fn write_framebuffer(
+    bs: &BootServices,
+    info: &mut InfoWriter,
+    preferred: Option<(u32, u32, u32)>,
+) -> Result<()> {
+    let Ok(handle) = bs.get_handle_for_protocol::<GraphicsOutput>() else {
+        return Ok(());
+    };
+    let mut gop = get_protocol::<GraphicsOutput>(bs, handle)?;
+    if let Some((width, height, _)) = preferred.filter(|x| x.0 != 0 && x.1 != 0) {
+        let resolution = (width as usize, height as usize);
+        let mode = gop.modes(bs).find(|x| x.info().resolution() == resolution);
+        if let Some(mode) = mode {
+            gop.set_mode(&mode).core_err()?;
+        }
+    }
+    let mode = gop.current_mode_info();
+    // Position and size of red, green and blue.
+    let field = |x: u32| [x.trailing_zeros() as u8, x.count_ones() as u8];
+    let colors = match mode.pixel_format() {
+        PixelFormat::Rgb => [0, 8, 8, 8, 16, 8],
+        PixelFormat::Bgr => [16, 8, 8, 8, 0, 8],
+        PixelFormat::Bitmask => {
+            let Some(mask) = mode.pixel_bitmask() else {
+                return Ok(());
+            };
+            let [red, green, blue] = [mask.red, mask.green, mask.blue].map(field);
+            [red[0], red[1], green[0], green[1], blue[0], blue[1]]
+        }
+        PixelFormat::BltOnly => return Ok(()),
+    };
+    let (width, height) = mode.resolution();
+    let pitch = mode.stride() as u32 * 4;
+    let address = gop.frame_buffer().as_mut_ptr() as u64;
+    info.tag(
+        tag::FRAMEBUFFER,
+        &[
+            &address.to_le_bytes(),
+            &pitch.to_le_bytes(),
+            &(width as u32).to_le_bytes(),
+            &(height as u32).to_le_bytes(),
+            // 32 bits per pixel, of direct RGB.
+            &[32, 1, 0, 0],
+            &colors,
+        ],
+    )
+    .core_err()?;
+    Ok(())
+}
+
+/// Write copies of ACPI RSDP, as kernels can not search for it in BIOS memory on EFI.
+fn write_acpi(st: &SystemTable<Boot>, info: &mut InfoWriter) -> Result<(), TooLarge> {
+    for entry in st.config_table() {
+        let rsdp = entry.address.cast::<u8>();
+        let (ty, size) = if entry.guid == ACPI2_GUID {
+            // Length of RSDP of ACPI 2.0 and later is in it.
+            let size = unsafe { rsdp.add(20).cast::<u32>().read_unaligned() };
+            (tag::ACPI_NEW, size as usize)
+        } else if entry.guid == ACPI_GUID {
+            (tag::ACPI_OLD, 20)
+        } else {
+            continue;
+        };
+        info.tag(ty, &[unsafe { core::slice::from_raw_parts(rsdp, size) }])?;
+    }
+    Ok(())
+}
+
+/// Type of memory map entry for EFI memory of `ty`.
+///
+/// Memory of boot services is reserved while they are running.
+fn memory_type(ty: MemoryType, boot_services: bool) -> u32 {
+    match ty {
+        MemoryType::CONVENTIONAL | MemoryType::LOADER_CODE | MemoryType::LOADER_DATA => {
+            memory::AVAILABLE
+        }
+        MemoryType::BOOT_SERVICES_CODE | MemoryType::BOOT_SERVICES_DATA if !boot_services => {
+            memory::AVAILABLE
+        }
+        MemoryType::ACPI_RECLAIM => memory::ACPI_RECLAIMABLE,
+        MemoryType::ACPI_NON_VOLATILE => memory::NVS,
+        MemoryType::UNUSABLE => memory::BADRAM,
+        _ => memory::RESERVED,
+    }
+}
+
+/// Write the memory map, and the EFI memory map as firmware gives.
+fn write_memory_maps(
+    info: &mut InfoWriter,
+    map: &MemoryMap,
+    boot_services: bool,
+) -> Result<(), TooLarge> {
+    let start = info.begin(tag::MMAP)?;
+    // Entry size and version.
+    info.push(&24u32.to_le_bytes())?;
+    info.push(&0u32.to_le_bytes())?;
+    for i in map.entries() {
+        info.push(&i.phys_start.to_le_bytes())?;
+        info.push(&(i.page_count * PAGE_SIZE).to_le_bytes())?;
+        info.push(&memory_type(i.ty, boot_services).to_le_bytes())?;
+        info.push(&[0; 4])?;
+    }
+    info.end(start)?;
+    let start = info.begin(tag::EFI_MMAP)?;
+    info.push(&(size_of::<MemoryDescriptor>() as u32).to_le_bytes())?;
+    info.push(&MemoryDescriptor::VERSION.to_le_bytes())?;
+    for i in map.entries() {
+        info.push(&i.ty.0.to_le_bytes())?;
+        info.push(&[0; 4])?;
+        info.push(&i.phys_start.to_le_bytes())?;
+        info.push(&i.virt_start.to_le_bytes())?;
+        info.push(&i.page_count.to_le_bytes())?;
+        info.push(&i.att.bits().to_le_bytes())?;
+    }
+    info.end(start)
+}
+
+/// Load and boot a multiboot2 kernel with its modules.
+pub fn boot(kernel: &ImageLocation, cmdline: &str, modules: &[Module]) -> Result<!> {
+    let st = system_table();
+    let bs = st.boot_services();
+    let image = read_image(kernel).context("failed to read kernel")?;
+    let header = Header::parse(&image)?;
+    let (segments, elf_entry) = header.layout(&image)?;
+    let amd64 = header.efi_boot_services && header.efi_amd64_entry.is_some();
+    let entry = match amd64 {
+        true => header.efi_amd64_entry.map(u64::from),
+        false => header.entry.map(u64::from).or(elf_entry),
+    }
+    .ok_or_else(|| anyhow!("kernel has no entry."))?;
+    if !amd64 && entry > u32::MAX.into() {
+        return Err(anyhow!("entry of kernel {:#x} is above 4 GiB.", entry));
+    }
+    load_kernel(bs, &image, &segments)?;
+
+    let mut loaded = Vec::new();
+    for module in modules {
+        let data = read_image(&module.image)
+            .with_context(|| format!("failed to read module {:?}", module.image))?;
+        let buf = allocate_low(bs, data.len())?;
+        buf.copy_from_slice(&data);
+        let start = buf.as_ptr() as u64;
+        let end = start.checked_add(data.len() as u64);
+        let (Ok(start), Some(Ok(end))) = (u32::try_from(start), end.map(u32::try_from)) else {
+            return Err(anyhow!(
+                "module {:?} does not end below 4 GiB.",
+                module.image
+            ));
+        };
+        loaded.push((start, end, module.cmdline.as_str()));
+    }
+
+    let map = bs.memory_map_size();
+    let size = INFO_RESERVE
+        + cmdline.len()
+        + modules.iter().map(|x| 24 + x.cmdline.len()).sum::<usize>()
+        + (map.map_size / map.entry_size + MMAP_SLACK) * MMAP_ENTRY;
+    let buf = allocate_low(bs, size)?;
+    let address = buf.as_ptr() as u64;
+    let mut info = InfoWriter::new(buf);
+    info.string(tag::CMDLINE, cmdline).core_err()?;
+    info.string(tag::BOOT_LOADER_NAME, "b2").core_err()?;
+    for (start, end, cmdline) in loaded {
+        info.module(start, end, cmdline).core_err()?;
+    }
+    write_framebuffer(bs, &mut info, header.framebuffer)?;
+    info.tag(tag::EFI64, &[&(st.as_ptr() as u64).to_le_bytes()])
+        .core_err()?;
+    info.tag(
+        tag::EFI64_IH,
+        &[&(bs.image_handle().as_ptr() as u64).to_le_bytes()],
+    )
+    .core_err()?;
+    write_acpi(&st, &mut info).core_err()?;
+    // Memory maps are written last, as they change until the kernel is entered.
+    let given =
+        |x| info.has(x) || x == tag::MMAP || x == tag::EFI_MMAP || (amd64 && x == tag::EFI_BS);
+    if let Some(ty) = header.missing(given) {
+        return Err(anyhow!(
+            "kernel requires boot information of type {}, which b2 does not give.",
+            ty
+        ));
+    }
+    info!(
+        "Booting multiboot2 kernel at {:#x}, cmdline {}",
+        entry, cmdline
+    );
+
+    if amd64 {
+        info.tag(tag::EFI_BS, &[]).core_err()?;
+        let mut mmap = vec![0u8; map.map_size + MMAP_SLACK * map.entry_size];
+        let memory_map = bs.memory_map(&mut mmap).core_err()?;
+        write_memory_maps(&mut info, &memory_map, true).core_err()?;
+        info.finish().core_err()?;
+        unsafe { enter_amd64(entry, address) }
+    }
+    let low = allocate_low(bs, PAGE_SIZE as usize)?.as_ptr() as u32;
+    let (_, memory_map) = st.exit_boot_services(MemoryType::LOADER_DATA);
+    // Nothing can be reported now. Room for the memory map is ensured above, but the kernel must
+    // not be entered with incomplete boot information if it still does not fit.
+    if write_memory_maps(&mut info, &memory_map, false)
+        .and_then(|_| info.finish())
+        .is_err()
+    {
+        halt();
+    }
+    unsafe { enter_i386(entry as u32, address as u32, low) }
+}
@@ -1,42 +1,81 @@
-use crate::io::console::{AcceleratorKey, Key};
+use crate::platform::efi::input::{
+    KeyData, LEFT_ALT_PRESSED, LEFT_CONTROL_PRESSED, LEFT_SHIFT_PRESSED, RIGHT_ALT_PRESSED,
+    RIGHT_CONTROL_PRESSED, RIGHT_SHIFT_PRESSED, SHIFT_STATE_VALID,
+};
+use io::console::{AcceleratorKey, Key, ModifierKey};
 use uefi::proto::console::text::{Key as EFIKey, ScanCode};
 
-impl From<EFIKey> for Key {
-    fn from(value: EFIKey) -> Self {
-        match value {
-            EFIKey::Printable(u) => {
-                let u: char = u.into();
-                match u {
-                    '\x08' => Self::Accelerator(AcceleratorKey::Backspace),
-                    '\t' => Self::Accelerator(AcceleratorKey::Tab),
-                    '\r' => Self::Accelerator(AcceleratorKey::Enter),
-                    k => Self::Printable(k),
-                }
+/// Key of Simple Text Input, without modifiers.
+pub fn key_of(value: EFIKey) -> Key {
+    match value {
+        EFIKey::Printable(u) => {
+            let u: char = u.into();
+            match u {
+                '\x08' => Key::Accelerator(AcceleratorKey::Backspace),
+                '\t' => Key::Accelerator(AcceleratorKey::Tab),
+                '\r' => Key::Accelerator(AcceleratorKey::Enter),
+                // Most firmware turns Ctrl-<letter> into control chars.
+                k @ '\x01'..='\x1a' => Key::Chord(ModifierKey::Ctrl, (b'a' + k as u8 - 1) as char),
+                k => Key::Printable(k),
             }
-            EFIKey::Special(ScanCode::UP) => Self::Accelerator(AcceleratorKey::Up),
-            EFIKey::Special(ScanCode::DOWN) => Self::Accelerator(AcceleratorKey::Down),
-            EFIKey::Special(ScanCode::LEFT) => Self::Accelerator(AcceleratorKey::Left),
-            EFIKey::Special(ScanCode::RIGHT) => Self::Accelerator(AcceleratorKey::Right),
-            EFIKey::Special(ScanCode::PAGE_UP) => Self::Accelerator(AcceleratorKey::PgUp),
-            EFIKey::Special(ScanCode::PAGE_DOWN) => Self::Accelerator(AcceleratorKey::PgDn),
-            EFIKey::Special(ScanCode::ESCAPE) => Self::Accelerator(AcceleratorKey::Esc),
-            EFIKey::Special(ScanCode::HOME) => Self::Accelerator(AcceleratorKey::Home),
-            EFIKey::Special(ScanCode::END) => Self::Accelerator(AcceleratorKey::End),
-            EFIKey::Special(ScanCode::INSERT) => Self::Accelerator(AcceleratorKey::Insert),
-            EFIKey::Special(ScanCode::DELETE) => Self::Accelerator(AcceleratorKey::Delete),
-            EFIKey::Special(ScanCode::FUNCTION_1) => Self::Accelerator(AcceleratorKey::F(1)),
-            EFIKey::Special(ScanCode::FUNCTION_2) => Self::Accelerator(AcceleratorKey::F(2)),
-            EFIKey::Special(ScanCode::FUNCTION_3) => Self::Accelerator(AcceleratorKey::F(3)),
-            EFIKey::Special(ScanCode::FUNCTION_4) => Self::Accelerator(AcceleratorKey::F(4)),
-            EFIKey::Special(ScanCode::FUNCTION_5) => Self::Accelerator(AcceleratorKey::F(5)),
-            EFIKey::Special(ScanCode::FUNCTION_6) => Self::Accelerator(AcceleratorKey::F(6)),
-            EFIKey::Special(ScanCode::FUNCTION_7) => Self::Accelerator(AcceleratorKey::F(7)),
-            EFIKey::Special(ScanCode::FUNCTION_8) => Self::Accelerator(AcceleratorKey::F(8)),
-            EFIKey::Special(ScanCode::FUNCTION_9) => Self::Accelerator(AcceleratorKey::F(9)),
-            EFIKey::Special(ScanCode::FUNCTION_10) => Self::Accelerator(AcceleratorKey::F(10)),
-            EFIKey::Special(ScanCode::FUNCTION_11) => Self::Accelerator(AcceleratorKey::F(11)),
-            EFIKey::Special(ScanCode::FUNCTION_12) => Self::Accelerator(AcceleratorKey::F(12)),
-            EFIKey::Special(k) => Self::Unknown(k.0 as u8),
+        }
+        EFIKey::Special(ScanCode::UP) => Key::Accelerator(AcceleratorKey::Up),
+        EFIKey::Special(ScanCode::DOWN) => Key::Accelerator(AcceleratorKey::Down),
+        EFIKey::Special(ScanCode::LEFT) => Key::Accelerator(AcceleratorKey::Left),
+        EFIKey::Special(ScanCode::RIGHT) => Key::Accelerator(AcceleratorKey::Right),
+        EFIKey::Special(ScanCode::PAGE_UP) => Key::Accelerator(AcceleratorKey::PgUp),
+        EFIKey::Special(ScanCode::PAGE_DOWN) => Key::Accelerator(AcceleratorKey::PgDn),
+        EFIKey::Special(ScanCode::ESCAPE) => Key::Accelerator(AcceleratorKey::Esc),
+        EFIKey::Special(ScanCode::HOME) => Key::Accelerator(AcceleratorKey::Home),
+        EFIKey::Special(ScanCode::END) => Key::Accelerator(AcceleratorKey::End),
+        EFIKey::Special(ScanCode::INSERT) => Key::Accelerator(AcceleratorKey::Insert),
+        EFIKey::Special(ScanCode::DELETE) => Key::Accelerator(AcceleratorKey::Delete),
+        EFIKey::Special(ScanCode::FUNCTION_1) => Key::Accelerator(AcceleratorKey::F(1)),
+        EFIKey::Special(ScanCode::FUNCTION_2) => Key::Accelerator(AcceleratorKey::F(2)),
+        EFIKey::Special(ScanCode::FUNCTION_3) => Key::Accelerator(AcceleratorKey::F(3)),
+        EFIKey::Special(ScanCode::FUNCTION_4) => Key::Accelerator(AcceleratorKey::F(4)),
+        EFIKey::Special(ScanCode::FUNCTION_5) => Key::Accelerator(AcceleratorKey::F(5)),
+        EFIKey::Special(ScanCode::FUNCTION_6) => Key::Accelerator(AcceleratorKey::F(6)),
+        EFIKey::Special(ScanCode::FUNCTION_7) => Key::Accelerator(AcceleratorKey::F(7)),
+        EFIKey::Special(ScanCode::FUNCTION_8) => Key::Accelerator(AcceleratorKey::F(8)),
+        EFIKey::Special(ScanCode::FUNCTION_9) => Key::Accelerator(AcceleratorKey::F(9)),
+        EFIKey::Special(ScanCode::FUNCTION_10) => Key::Accelerator(AcceleratorKey::F(10)),
+        EFIKey::Special(ScanCode::FUNCTION_11) => Key::Accelerator(AcceleratorKey::F(11)),
+        EFIKey::Special(ScanCode::FUNCTION_12) => Key::Accelerator(AcceleratorKey::F(12)),
+        EFIKey::Special(k) => Key::Unknown(k.0 as u8),
+    }
+}
+
+/// Modifier held in a shift state, in the order of `ModifierKey`.
+pub fn modifier_of(state: u32) -> Option<ModifierKey> {
+    if state & SHIFT_STATE_VALID == 0 {
+        None
+    } else if state & (LEFT_CONTROL_PRESSED | RIGHT_CONTROL_PRESSED) != 0 {
+        Some(ModifierKey::Ctrl)
+    } else if state & (LEFT_ALT_PRESSED | RIGHT_ALT_PRESSED) != 0 {
+        Some(ModifierKey::Alt)
+    } else if state & (LEFT_SHIFT_PRESSED | RIGHT_SHIFT_PRESSED) != 0 {
+        Some(ModifierKey::Shift)
+    } else {
+        None
+    }
+}
+
+impl From<KeyData> for Key {
+    fn from(value: KeyData) -> Self {
+        let key = key_of(EFIKey::from(value.key));
+        let state = value.key_state.key_shift_state;
+        let modifier = modifier_of(state);
+        match (modifier, key) {
+            // Shift is already applied to printable chars.
+            (Some(ModifierKey::Shift), key @ Key::Printable(_)) => key,
+            // AltGr is used to type chars on many layouts.
+            (Some(ModifierKey::Alt), key @ Key::Printable(_)) if state & LEFT_ALT_PRESSED == 0 => {
+                key
+            }
+            (Some(modifier), Key::Printable(k)) => Self::Chord(modifier, k.to_ascii_lowercase()),
+            (Some(modifier), Key::Accelerator(k)) => Self::ChordAccelerator(modifier, k),
+            (_, key) => key,
         }
     }
 }
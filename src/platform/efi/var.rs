@@ -0,0 +1,68 @@
+//! Access to EFI variables.
+//!
+//! Variables of b2 are under `B2_VENDOR`, and others are reached with `*_vendor_var`.
+
+use alloc::{string::ToString, vec::Vec};
+use anyhow::{anyhow, Result};
+use uefi::{
+    table::runtime::{VariableAttributes, VariableVendor},
+    CString16, Status,
+};
+use uefi_services::system_table;
+
+use crate::platform::efi::{error::EfiError, B2_VENDOR};
+
+fn var_name(name: &str) -> Result<CString16> {
+    CString16::try_from(name).map_err(|_| anyhow!("invalid variable name {}.", name))
+}
+
+/// Read a b2 variable.
+pub fn get_var(name: &str) -> Result<Vec<u8>> {
+    get_vendor_var(name, &B2_VENDOR)
+}
+
+/// Write a non-volatile b2 variable.
+pub fn set_var(name: &str, data: &[u8]) -> Result<()> {
+    set_vendor_var(name, &B2_VENDOR, data)
+}
+
+/// Read a variable of any vendor.
+pub fn get_vendor_var(name: &str, vendor: &VariableVendor) -> Result<Vec<u8>> {
+    let st = system_table();
+    let rs = st.runtime_services();
+    rs.get_variable_boxed(&var_name(name)?, vendor)
+        .map(|x| x.0.into_vec())
+        .map_err(|e| match e.status() {
+            Status::NOT_FOUND => anyhow!(EfiError::NoEfiVariable(name.to_string())),
+            _ => anyhow!(EfiError::GetEfiVariable(
+                e.to_err_without_payload(),
+                name.to_string()
+            )),
+        })
+}
+
+/// Write a non-volatile variable of any vendor.
+pub fn set_vendor_var(name: &str, vendor: &VariableVendor, data: &[u8]) -> Result<()> {
+    let st = system_table();
+    let rs = st.runtime_services();
+    rs.set_variable(
+        &var_name(name)?,
+        vendor,
+        VariableAttributes::NON_VOLATILE
+            | VariableAttributes::BOOTSERVICE_ACCESS
+            | VariableAttributes::RUNTIME_ACCESS,
+        data,
+    )
+    .map_err(|e| anyhow!(EfiError::WriteEfiVariable(e, name.to_string())))
+}
+
+/// Delete a variable of any vendor.
+pub fn delete_vendor_var(name: &str, vendor: &VariableVendor) -> Result<()> {
+    let st = system_table();
+    let rs = st.runtime_services();
+    rs.delete_variable(&var_name(name)?, vendor)
+        .map_err(|e| match e.status() {
+            Status::NOT_FOUND => anyhow!(EfiError::NoEfiVariable(name.to_string())),
+            _ => anyhow!(EfiError::WriteEfiVariable(e, name.to_string())),
+        })
+}
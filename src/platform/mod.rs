@@ -4,6 +4,9 @@ pub mod efi;
 #[cfg(target_os = "uefi")]
 pub use {efi::efi_error::ToError, uefi_services::println};
 
+#[cfg(target_os = "uefi")]
+pub use efi::file::list_dir;
+
 #[cfg(target_os = "uefi")]
 pub type PlatformFile = efi::file::EFIFile;
 